@@ -0,0 +1,13 @@
+cargo run -- \
+    --backend gpt-3.5-turbo \
+    --task_config scripts/declarative/arithmetic.toml \
+    --task_start_index 0 \
+    --task_end_index 3 \
+    --method_generate sample \
+    --method_evaluate vote \
+    --method_select greedy \
+    --n_generate_sample 5 \
+    --n_evaluate_sample 5 \
+    --n_select_sample 1 \
+    --prompt_sample cot \
+    ${@}
@@ -0,0 +1,110 @@
+use crate::models::Prompt;
+use std::{
+	path::{Path, PathBuf},
+	sync::OnceLock,
+};
+
+/// Where completions are stored when caching is enabled without `--cache_dir`.
+pub const DEFAULT_CACHE_DIR: &str = "./cache";
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// A completion request and, once answered, its outputs. Stored as `<cache_dir>/<key>.json`.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Entry {
+	pub model: String,
+	pub temperature: f32,
+	pub max_tokens: u16,
+	pub n: isize,
+	pub stop: Option<String>,
+	pub prompt: String,
+	#[serde(default)]
+	pub outputs: Vec<String>,
+}
+
+impl Entry {
+	pub fn new(model: &str, temperature: f32, max_tokens: u16, n: isize, stop: Option<&str>, prompt: &Prompt) -> Self {
+		Entry {
+			model: model.to_string(),
+			temperature,
+			max_tokens,
+			n,
+			stop: stop.map(|s| s.to_string()),
+			prompt: prompt.to_string(),
+			outputs: vec![],
+		}
+	}
+
+	/// Hash of the request, so a cache directory can be reused.
+	pub fn key(&self) -> String {
+		let request = format!("{}\0{}\0{}\0{}\0{:?}\0{}", self.model, self.temperature, self.max_tokens, self.n, self.stop, self.prompt);
+		format!("{:016x}", hash(&request))
+	}
+}
+
+/// FNV-1a hash of `text`, which unlike `DefaultHasher` stays the same across builds.
+pub fn hash(text: &str) -> u64 {
+	text.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Turns on the completion cache, stored in `dir`. Must be called before the first completion; enabling the same directory again is fine.
+pub fn enable(dir: &Path) -> anyhow::Result<()> {
+	std::fs::create_dir_all(dir).map_err(|e| anyhow::anyhow!("Cannot create cache directory {}: {}", dir.display(), e))?;
+	match CACHE_DIR.get() {
+		Some(set) if set == dir => Ok(()),
+		_ => CACHE_DIR.set(dir.to_path_buf()).map_err(|_| anyhow::anyhow!("Cache directory already set")),
+	}
+}
+
+/// Whether [`enable`] was called.
+pub fn enabled() -> bool {
+	CACHE_DIR.get().is_some()
+}
+
+/// The cached outputs of `request`, if caching is enabled and it was answered before.
+pub fn get(request: &Entry) -> Option<Vec<String>> {
+	let path = CACHE_DIR.get()?.join(format!("{}.json", request.key()));
+	let entry: Entry = serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()?;
+	Some(entry.outputs)
+}
+
+/// Stores an answered request; a no-op while caching is disabled. A cache that cannot be written only costs a repeated request.
+pub fn put(entry: &Entry) {
+	let Some(dir) = CACHE_DIR.get() else {
+		return;
+	};
+	let path = dir.join(format!("{}.json", entry.key()));
+	if let Err(e) = serde_json::to_string(entry).map_err(anyhow::Error::from).and_then(|json| Ok(std::fs::write(&path, json)?)) {
+		eprintln!("Could not cache completion in {}: {}", path.display(), e);
+	}
+}
+
+/// Every entry in `dir` with its key, sorted by key.
+pub fn entries(dir: &Path) -> anyhow::Result<Vec<(String, Entry)>> {
+	let mut entries = vec![];
+	if !dir.is_dir() {
+		return Ok(entries);
+	}
+	for file in std::fs::read_dir(dir)? {
+		let path = file?.path();
+		let Some(key) = path.file_stem().and_then(|s| s.to_str()).filter(|_| path.extension().is_some_and(|ext| ext == "json")) else {
+			continue;
+		};
+		let entry = serde_json::from_str(&std::fs::read_to_string(&path)?).map_err(|e| anyhow::anyhow!("Invalid cache entry {}: {}", path.display(), e))?;
+		entries.push((key.to_string(), entry));
+	}
+	entries.sort_by(|a, b| a.0.cmp(&b.0));
+	Ok(entries)
+}
+
+/// Deletes the entries of `dir` for which `keep` is false and returns how many went.
+pub fn clear(dir: &Path, keep: impl Fn(&Entry) -> bool) -> anyhow::Result<usize> {
+	let mut removed = 0;
+	for (key, entry) in entries(dir)? {
+		if !keep(&entry) {
+			std::fs::remove_file(dir.join(format!("{key}.json")))?;
+			removed += 1;
+		}
+	}
+	Ok(removed)
+}
@@ -0,0 +1,388 @@
+use crate::{
+	dataset::{DatasetFormat, DatasetOptions},
+	log::DEFAULT_LOG_DIR,
+	models::{ContextPolicy, ModelConfig, MODELS},
+	select::Selector,
+	tasks::{DeclarativeTask, TaskOptions, TaskRegistry, TaskSpec, ToTTask},
+	tokens,
+};
+use std::{ffi::OsString, path::PathBuf};
+
+mod cache;
+mod eval;
+mod experiment;
+mod play;
+mod render;
+mod run;
+mod solve;
+mod stats;
+
+/// One flag of a subcommand, for its generated `--help`. `value` is empty for switches.
+struct Flag {
+	name: &'static str,
+	value: &'static str,
+	help: &'static str,
+}
+
+const fn flag(name: &'static str, value: &'static str, help: &'static str) -> Flag {
+	Flag { name, value, help }
+}
+
+struct Command {
+	name: &'static str,
+	/// Positional arguments, as shown in the usage line.
+	args: &'static str,
+	about: &'static str,
+	flags: &'static [&'static [Flag]],
+}
+
+const COMMANDS: [&Command; 7] = [&run::COMMAND, &solve::COMMAND, &eval::COMMAND, &stats::COMMAND, &render::COMMAND, &cache::COMMAND, &play::COMMAND];
+
+const MODEL_FLAGS: &[Flag] = &[
+	flag("--backend", "MODEL", "model for every role without its own, gpt-4 (default) or gpt-3.5-turbo"),
+	flag("--temperature", "T", "sampling temperature for every role without its own, 0.7 by default"),
+	flag("--{generator,evaluator,scorer}_model", "MODEL", "model of one role"),
+	flag("--{generator,evaluator,scorer}_temperature", "T", "temperature of one role"),
+	flag("--{generator,evaluator,scorer}_max_tokens", "N", "completion tokens of one role, 1000 by default"),
+	flag("--context_policy", "POLICY", "over-long prompts: fail (default), truncate_examples (with --chat_prompts), summarise or drop_candidates"),
+	flag("--tokenizer_dir", "DIR", "directory with <encoding>.tiktoken files, ./data/tokenizers by default; none ships, so without one token counts are estimated"),
+	flag("--cache", "", "reuse completions of identical requests from the cache directory"),
+	flag("--cache_dir", "DIR", "completion cache directory, ./cache by default; implies --cache"),
+];
+
+const TASK_FLAGS: &[Flag] = &[
+	flag("--task", "NAME", "built-in task: game24, countdown, text or crosswords"),
+	flag("--task_config", "FILE", "TOML, YAML or JSON task spec, instead of a built-in task"),
+	flag("--task_file_path", "PATH", "dataset, replacing the task's default; relative to its data directory"),
+	flag("--dataset_format", "FORMAT", "csv, jsonl, json or lines; inferred from the extension by default"),
+	flag("--input_field", "FIELD", "column or field holding the puzzle"),
+	flag("--answer_field", "FIELD", "column or field holding the gold answer"),
+	flag("--id_field", "FIELD", "column or field identifying a puzzle, its row by default"),
+	flag("--split", "NAME", "split file to read when the dataset is a directory, test by default"),
+	flag("--prompt_dir", "DIR", "optional prompt overrides as <dir>/<task>/<name>.txt, ./prompts by default"),
+	flag("--chat_prompts", "", "send system, few-shot and query turns instead of one user message"),
+	flag("--task_param", "KEY=VALUE", "setting of the task's own; repeatable. countdown: target=N, operators=+-*; crosswords: word_list=FILE; text: n_score_sample=N"),
+];
+
+const SEARCH_FLAGS: &[Flag] = &[
+	flag("--naive_run", "", "sample answers directly instead of searching"),
+	flag("--prompt_sample", "KIND", "sampling prompt: standard or cot"),
+	flag("--method_generate", "METHOD", "sample or propose"),
+	flag("--method_evaluate", "METHOD", "value or vote"),
+	flag("--method_select", "METHOD", "greedy, sample, sample_without_replacement, softmax or epsilon_greedy"),
+	flag("--select_temperature", "T", "softmax selection temperature, 1.0 by default"),
+	flag("--select_epsilon", "P", "epsilon-greedy exploration rate, 0.1 by default"),
+	flag("--n_generate_sample", "N", "samples per node, 1 by default"),
+	flag("--n_evaluate_sample", "N", "value or vote samples per step, 1 by default"),
+	flag("--n_select_sample", "N", "candidates kept per step, 1 by default"),
+	flag("--proposals_per_node", "K", "best proposals kept per node with --method_generate propose, all by default"),
+	flag("--seed", "N", "seed for selection and dataset sampling, random by default"),
+];
+
+const HELP_FLAG: &[Flag] = &[flag("-h, --help", "", "print this help")];
+
+fn flag_name(flag: &Flag) -> String {
+	match flag.value {
+		"" => flag.name.to_string(),
+		value => format!("{} <{}>", flag.name, value),
+	}
+}
+
+fn help(command: &Command) -> String {
+	let usage = format!("{} {} [FLAGS] {}", env!("CARGO_PKG_NAME"), command.name, command.args);
+	let mut help = format!("{}\n\nUsage: {}\n", command.about, usage.trim_end());
+	let width = command.flags.iter().flat_map(|flags| flags.iter()).map(|flag| flag_name(flag).len()).max().unwrap_or(0);
+	for flags in command.flags {
+		help.push('\n');
+		for flag in *flags {
+			help.push_str(&format!("  {:width$}  {}\n", flag_name(flag), flag.help));
+		}
+	}
+	help
+}
+
+fn usage() -> String {
+	let mut usage = format!("Tree of Thoughts search over LLM completions.\n\nUsage: {} <COMMAND> [FLAGS]\n\nCommands:\n", env!("CARGO_PKG_NAME"));
+	for command in COMMANDS {
+		usage.push_str(&format!("  {:8}{}\n", command.name, command.about));
+	}
+	usage.push_str("  help    print the help of a command\n\nWithout a command the flags are those of `run`. See `<COMMAND> --help` for the flags of each command.\n");
+	usage
+}
+
+/// Prints the help of `command` if it was asked for.
+fn wants_help(args: &mut pico_args::Arguments, command: &Command) -> bool {
+	let wants = args.contains(["-h", "--help"]);
+	if wants {
+		print!("{}", help(command));
+	}
+	wants
+}
+
+/// The positional arguments left after every flag was read; a leftover flag is an error.
+fn positionals(args: pico_args::Arguments, command: &Command) -> anyhow::Result<Vec<String>> {
+	let mut positionals = vec![];
+	for arg in args.finish() {
+		let arg = arg.into_string().map_err(|arg: OsString| anyhow::anyhow!("Invalid argument: {:?}", arg))?;
+		if arg.starts_with('-') && arg.len() > 1 {
+			anyhow::bail!("Unknown flag {} for `{}`, see `{} --help`", arg, command.name, command.name);
+		}
+		positionals.push(arg);
+	}
+	Ok(positionals)
+}
+
+/// Like [`positionals`], for commands that take none.
+fn finish(args: pico_args::Arguments, command: &Command) -> anyhow::Result<()> {
+	let positionals = positionals(args, command)?;
+	if !positionals.is_empty() {
+		anyhow::bail!("Unexpected arguments {:?} for `{}`, see `{} --help`", positionals, command.name, command.name);
+	}
+	Ok(())
+}
+
+/// Every setting of a run; serialized into its manifest and hashed into its default name.
+#[derive(serde::Serialize)]
+struct Opts {
+	backend: Option<String>,
+	temperature: f64,
+
+	generator: ModelConfig,
+	evaluator: ModelConfig,
+	scorer: ModelConfig,
+
+	task: String,
+	task_config: Option<String>,
+	task_options: TaskOptions,
+	task_start_index: Option<isize>,
+	task_end_index: Option<isize>,
+	indices: Option<Vec<usize>>,
+
+	naive_run: bool,
+	prompt_sample: Option<String>,
+
+	method_generate: Option<String>,
+	method_evaluate: Option<String>,
+	method_select: Option<Selector>,
+
+	n_generate_sample: isize,
+	n_evaluate_sample: isize,
+	n_select_sample: isize,
+	proposals_per_node: Option<usize>,
+
+	seed: u64,
+
+	#[serde(skip)]
+	log_dir: PathBuf,
+	#[serde(skip)]
+	run_name: Option<String>,
+}
+
+/// Model settings shared by every role, read from the [`MODEL_FLAGS`]; `None` where the flag was not given.
+struct Backend {
+	backend: Option<String>,
+	temperature: Option<f64>,
+	context_policy: Option<ContextPolicy>,
+}
+
+impl Backend {
+	/// Reads the `[model, temperature, max_tokens]` flags of one role, falling back to the backend and global temperature.
+	fn model_config(&self, args: &mut pico_args::Arguments, keys: [&'static str; 3]) -> anyhow::Result<ModelConfig> {
+		let model: Option<String> = args.opt_value_from_str(keys[0])?;
+		self.role_config(model, args.opt_value_from_str(keys[1])?, args.opt_value_from_str(keys[2])?, None)
+	}
+
+	/// The settings of one role from its own flag values, the shared flags, `base` (such as the scorer of a logged run) and the defaults, in that order.
+	fn role_config(&self, model: Option<String>, temperature: Option<f32>, max_tokens: Option<u16>, base: Option<&ModelConfig>) -> anyhow::Result<ModelConfig> {
+		let model = model.or_else(|| self.backend.clone()).or_else(|| base.map(|base| base.model.clone())).unwrap_or_else(|| "gpt-4".to_string());
+		let temperature = temperature.or(self.temperature.map(|t| t as f32)).or(base.map(|base| base.temperature)).unwrap_or(0.7);
+		let max_tokens = max_tokens.or(base.map(|base| base.max_tokens)).unwrap_or(1000);
+		let context_policy = self.context_policy.or(base.map(|base| base.context_policy)).unwrap_or_default();
+		ModelConfig::new(&model, temperature, max_tokens, context_policy)
+	}
+}
+
+/// Reads the backend, temperature, context and cache flags; the per-role flags are left to [`Backend::model_config`].
+fn parse_backend(args: &mut pico_args::Arguments) -> anyhow::Result<Backend> {
+	let backend: Option<String> = args.opt_value_from_str("--backend")?;
+	if let Some(backend) = backend.as_deref().filter(|backend| !MODELS.contains(backend)) {
+		anyhow::bail!("Invalid backend: {} (expected one of {})", backend, MODELS.join(", "));
+	}
+
+	let temperature = args.opt_value_from_str("--temperature")?;
+
+	let context_policy: Option<String> = args.opt_value_from_str("--context_policy")?;
+	let context_policy = context_policy.as_deref().map(ContextPolicy::from_args).transpose()?;
+	if let Some(dir) = args.opt_value_from_str::<_, PathBuf>("--tokenizer_dir")? {
+		tokens::set_tokenizer_dir(&dir)?;
+	}
+
+	let cache_dir: Option<PathBuf> = args.opt_value_from_str("--cache_dir")?;
+	if args.contains("--cache") || cache_dir.is_some() {
+		crate::cache::enable(&cache_dir.unwrap_or_else(|| PathBuf::from(crate::cache::DEFAULT_CACHE_DIR)))?;
+	}
+	Ok(Backend { backend, temperature, context_policy })
+}
+
+/// Reads the [`TASK_FLAGS`] into the task name, its spec file and the options its factory gets.
+fn parse_task(args: &mut pico_args::Arguments) -> anyhow::Result<(String, Option<String>, TaskOptions)> {
+	let task = args.opt_value_from_str("--task")?.unwrap_or_default();
+	let task_config = args.opt_value_from_str("--task_config")?;
+	let file_path = args.opt_value_from_str("--task_file_path")?.unwrap_or_default();
+	let prompt_dir = args.opt_value_from_str("--prompt_dir")?;
+	let chat_prompts = args.contains("--chat_prompts");
+	let mut extra = std::collections::BTreeMap::new();
+	for param in args.values_from_str::<_, String>("--task_param")? {
+		let Some((key, value)) = param.split_once('=') else {
+			anyhow::bail!("Invalid --task_param {:?}, expected KEY=VALUE", param);
+		};
+		extra.insert(key.trim().to_string(), value.trim().to_string());
+	}
+
+	let dataset_format: Option<String> = args.opt_value_from_str("--dataset_format")?;
+	let dataset = DatasetOptions {
+		format: dataset_format.as_deref().map(DatasetFormat::from_args).transpose()?,
+		input_field: args.opt_value_from_str("--input_field")?,
+		answer_field: args.opt_value_from_str("--answer_field")?,
+		id_field: args.opt_value_from_str("--id_field")?,
+		split: args.opt_value_from_str("--split")?,
+		..Default::default()
+	};
+	Ok((
+		task,
+		task_config,
+		TaskOptions {
+			file_path,
+			dataset,
+			prompt_dir,
+			chat_prompts,
+			extra,
+		},
+	))
+}
+
+/// Builds the task named by `--task`, or the one described by `--task_config`, and returns it with its name.
+fn load_task(registry: &mut TaskRegistry, task: &str, task_config: Option<&str>, options: &TaskOptions) -> anyhow::Result<(String, Box<dyn ToTTask>)> {
+	let name = match task_config {
+		Some(task_config) => {
+			let spec = TaskSpec::load(task_config)?;
+			let name = spec.name.clone();
+			registry.register(&name, move |task_options| Ok(Box::new(DeclarativeTask::new(spec.clone(), task_options)?)));
+			name
+		}
+		None if task.is_empty() => anyhow::bail!("Missing --task (available: {}) or --task_config", registry.names().collect::<Vec<_>>().join(", ")),
+		None => task.to_string(),
+	};
+	let task = registry.get_task(&name, options)?;
+	Ok((name, task))
+}
+
+/// Reads a sample count flag, 1 by default; zero or negative counts are rejected.
+fn parse_count(args: &mut pico_args::Arguments, key: &'static str) -> anyhow::Result<isize> {
+	let n = args.opt_value_from_str(key)?.unwrap_or(1);
+	if n < 1 {
+		anyhow::bail!("Invalid {} {}, expected at least 1", key, n);
+	}
+	Ok(n)
+}
+
+/// Reads the model, task and search flags shared by `run` and `solve`.
+fn parse_opts(args: &mut pico_args::Arguments) -> anyhow::Result<Opts> {
+	let backend = parse_backend(args)?;
+	let generator = backend.model_config(args, ["--generator_model", "--generator_temperature", "--generator_max_tokens"])?;
+	let evaluator = backend.model_config(args, ["--evaluator_model", "--evaluator_temperature", "--evaluator_max_tokens"])?;
+	let scorer = backend.model_config(args, ["--scorer_model", "--scorer_temperature", "--scorer_max_tokens"])?;
+
+	let (task, task_config, task_options) = parse_task(args)?;
+	backend.context_policy.unwrap_or_default().check(task_options.chat_prompts)?;
+
+	let naive_run = args.contains("--naive_run");
+
+	let prompt_sample: Option<String> = args.opt_value_from_str("--prompt_sample")?;
+	match prompt_sample.as_deref() {
+		Some("standard" | "cot") | None => {}
+		sample => anyhow::bail!("Invalid prompt_sample: {:?} (expected standard or cot)", sample),
+	}
+
+	let method_generate: Option<String> = args.opt_value_from_str("--method_generate")?;
+	match method_generate.as_deref() {
+		Some("sample" | "propose") | None => {}
+		sample => anyhow::bail!("Invalid method_generate: {:?} (expected sample or propose)", sample),
+	}
+
+	let method_evaluate: Option<String> = args.opt_value_from_str("--method_evaluate")?;
+	match method_evaluate.as_deref() {
+		Some("value" | "vote") | None => {}
+		sample => anyhow::bail!("Invalid method_evaluate: {:?} (expected value or vote)", sample),
+	}
+
+	let method_select: Option<String> = args.opt_value_from_str("--method_select")?;
+	let select_temperature = args.opt_value_from_str("--select_temperature")?.unwrap_or(1.0f32);
+	let select_epsilon = args.opt_value_from_str("--select_epsilon")?.unwrap_or(0.1f32);
+	let method_select = method_select.map(|method| Selector::from_args(&method, select_temperature, select_epsilon)).transpose()?;
+	let n_generate_sample = parse_count(args, "--n_generate_sample")?;
+	let n_evaluate_sample = parse_count(args, "--n_evaluate_sample")?;
+	let n_select_sample = parse_count(args, "--n_select_sample")?;
+	let proposals_per_node = args.opt_value_from_str("--proposals_per_node")?;
+	// an unseeded run still draws and logs a seed so it can be replayed
+	let seed = args.opt_value_from_str("--seed")?.unwrap_or_else(rand::random::<u64>);
+	Ok(Opts {
+		backend: Some(backend.backend.unwrap_or_else(|| "gpt-4".to_string())),
+		temperature: backend.temperature.unwrap_or(0.7),
+		generator,
+		evaluator,
+		scorer,
+		task,
+		task_config,
+		task_options,
+		task_start_index: None,
+		task_end_index: None,
+		indices: None,
+		naive_run,
+		prompt_sample,
+		method_generate,
+		method_evaluate,
+		method_select,
+		n_generate_sample,
+		n_evaluate_sample,
+		n_select_sample,
+		proposals_per_node,
+		seed,
+		log_dir: PathBuf::from(DEFAULT_LOG_DIR),
+		run_name: None,
+	})
+}
+
+/// Runs the subcommand named on the command line, resolving `--task` in `registry`.
+/// Without a subcommand the arguments are those of `run`, as before subcommands existed.
+pub async fn run(registry: TaskRegistry) -> anyhow::Result<()> {
+	if std::env::args_os().len() <= 1 {
+		print!("{}", usage());
+		return Ok(());
+	}
+	let mut args = pico_args::Arguments::from_env();
+	let subcommand = args.subcommand()?;
+	if subcommand.is_none() && args.contains(["-h", "--help"]) {
+		print!("{}", usage());
+		return Ok(());
+	}
+	match subcommand.as_deref() {
+		None | Some("run") => run::run(args, registry).await,
+		Some("solve") => solve::run(args, registry).await,
+		Some("eval") => eval::run(args, registry).await,
+		Some("stats") => stats::run(args),
+		Some("render") => render::run(args),
+		Some("cache") => cache::run(args),
+		Some("play") => play::run(args).await,
+		Some("help") => {
+			let topic: Option<String> = args.opt_free_from_str()?;
+			match topic.as_deref().map(|topic| COMMANDS.iter().find(|command| command.name == topic).ok_or(topic)) {
+				None => print!("{}", usage()),
+				Some(Ok(command)) => print!("{}", help(command)),
+				Some(Err(topic)) => anyhow::bail!("Unknown command {:?}, see `help`", topic),
+			}
+			Ok(())
+		}
+		Some(command) => anyhow::bail!("Unknown command {:?}\n\n{}", command, usage()),
+	}
+}
@@ -0,0 +1,63 @@
+use super::{flag, positionals, wants_help, Command, Flag, HELP_FLAG};
+use crate::cache::{self, DEFAULT_CACHE_DIR};
+use std::{collections::BTreeMap, path::PathBuf};
+
+const CACHE_FLAGS: &[Flag] = &[
+	flag("--cache_dir", "DIR", "completion cache directory, ./cache by default"),
+	flag("--model", "MODEL", "only entries of this model"),
+];
+
+pub(super) const COMMAND: Command = Command {
+	name: "cache",
+	args: "[stats | list | show <KEY> | clear]",
+	about: "inspect or clear the completion cache",
+	flags: &[CACHE_FLAGS, HELP_FLAG],
+};
+
+pub(super) fn run(mut args: pico_args::Arguments) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let dir: PathBuf = args.opt_value_from_str("--cache_dir")?.unwrap_or_else(|| PathBuf::from(DEFAULT_CACHE_DIR));
+	let model: Option<String> = args.opt_value_from_str("--model")?;
+	let positionals = positionals(args, &COMMAND)?;
+	let matches = |entry: &cache::Entry| model.as_ref().is_none_or(|model| &entry.model == model);
+
+	match positionals.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+		[] | ["stats"] => {
+			let mut models = BTreeMap::<String, (usize, usize)>::new();
+			for (_, entry) in cache::entries(&dir)?.into_iter().filter(|(_, entry)| matches(entry)) {
+				let stats = models.entry(entry.model.clone()).or_default();
+				stats.0 += 1;
+				stats.1 += entry.outputs.len();
+			}
+			println!("{}: {} entries", dir.display(), models.values().map(|(entries, _)| entries).sum::<usize>());
+			for (model, (entries, outputs)) in models {
+				println!("  {}: {} entries, {} outputs", model, entries, outputs);
+			}
+		}
+		["list"] => {
+			for (key, entry) in cache::entries(&dir)?.into_iter().filter(|(_, entry)| matches(entry)) {
+				let prompt = entry.prompt.trim().lines().next().unwrap_or("").chars().take(60).collect::<String>();
+				println!("{}  {}  t={}  n={}  {}", key, entry.model, entry.temperature, entry.n, prompt);
+			}
+		}
+		["show", key] => {
+			let found = cache::entries(&dir)?.into_iter().filter(|(k, _)| k.starts_with(key)).collect::<Vec<_>>();
+			let [(key, entry)] = found.as_slice() else {
+				anyhow::bail!("{} entries match {:?} in {}", found.len(), key, dir.display());
+			};
+			println!("key: {}\nmodel: {}\ntemperature: {}\nmax_tokens: {}\nn: {}\nstop: {:?}", key, entry.model, entry.temperature, entry.max_tokens, entry.n, entry.stop);
+			println!("\nprompt:\n{}", entry.prompt);
+			for (i, output) in entry.outputs.iter().enumerate() {
+				println!("\noutput {}:\n{}", i + 1, output);
+			}
+		}
+		["clear"] => {
+			let removed = cache::clear(&dir, |entry| !matches(entry))?;
+			println!("Removed {} entries from {}", removed, dir.display());
+		}
+		other => anyhow::bail!("Unknown cache action {:?}, see `cache --help`", other.join(" ")),
+	}
+	Ok(())
+}
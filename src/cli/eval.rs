@@ -0,0 +1,165 @@
+use super::{flag, load_task, parse_backend, parse_task, positionals, stats::log_files, wants_help, Command, Flag, HELP_FLAG, TASK_FLAGS};
+use crate::{
+	log,
+	metrics::{self, Metrics},
+	models::ModelConfig,
+	tasks::{TaskOptions, TaskRegistry},
+};
+use std::path::{Path, PathBuf};
+
+const SCORER_FLAGS: &[Flag] = &[
+	flag("--backend", "MODEL", "scorer model unless --scorer_model is given; by default the run's scorer, else gpt-4"),
+	flag("--temperature", "T", "scorer temperature unless --scorer_temperature is given; by default the run's, else 0.7"),
+	flag("--scorer_model", "MODEL", "model of tasks that score with an LLM"),
+	flag("--scorer_temperature", "T", "temperature of the scorer"),
+	flag("--scorer_max_tokens", "N", "completion tokens of the scorer; by default the run's, else 1000"),
+	flag("--context_policy", "POLICY", "over-long prompts: fail, truncate_examples, summarise or drop_candidates; by default the run's"),
+	flag("--tokenizer_dir", "DIR", "directory with <encoding>.tiktoken files, ./data/tokenizers by default; none ships, so without one token counts are estimated"),
+	flag("--cache_dir", "DIR", "completion cache directory, ./cache by default"),
+	flag("--no_cache", "", "call the scorer even for requests it answered before"),
+];
+
+const OUTPUT_FLAGS: &[Flag] = &[flag("--dry_run", "", "print the rescored results without writing them")];
+
+pub(super) const COMMAND: Command = Command {
+	name: "eval",
+	args: "<LOG|DIR>...",
+	about: "rescore the final candidates of run logs with the task's current verifier, updating their infos and metrics",
+	flags: &[TASK_FLAGS, SCORER_FLAGS, OUTPUT_FLAGS, HELP_FLAG],
+};
+
+/// The task of a log and the options to load it with: the flags when `--task` or `--task_config` is given,
+/// else the run manifest; logs older than manifests sit directly in `logs/<task>/`.
+fn task_of(file: &Path, manifest: Option<&log::Manifest>, cli: &(String, Option<String>, TaskOptions)) -> anyhow::Result<(String, Option<String>, TaskOptions)> {
+	let (task, task_config, _) = cli;
+	if !task.is_empty() || task_config.is_some() {
+		return Ok(cli.clone());
+	}
+	let Some(manifest) = manifest else {
+		let dir = file.parent().and_then(|dir| dir.file_name()).and_then(|name| name.to_str()).unwrap_or_default();
+		return Ok((dir.to_string(), None, cli.2.clone()));
+	};
+	let task_config = serde_json::from_value(manifest.options["task_config"].clone())?;
+	let task_options = serde_json::from_value(manifest.options["task_options"].clone()).map_err(|e| anyhow::anyhow!("Invalid task options in the manifest of {}: {}", file.display(), e))?;
+	Ok((manifest.task.clone(), task_config, task_options))
+}
+
+/// Where the log is kept before its first rescoring overwrites it.
+fn backup_path(file: &Path) -> PathBuf {
+	let mut name = file.file_name().unwrap_or_default().to_os_string();
+	name.push(".bak");
+	file.with_file_name(name)
+}
+
+pub(super) async fn run(mut args: pico_args::Arguments, mut registry: TaskRegistry) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let no_cache = args.contains("--no_cache");
+	let dry_run = args.contains("--dry_run");
+	let backend = parse_backend(&mut args)?;
+	// rescoring the same candidates again should repeat the scorer's answers, not draw new ones
+	if !no_cache && !crate::cache::enabled() {
+		crate::cache::enable(Path::new(crate::cache::DEFAULT_CACHE_DIR))?;
+	}
+	let scorer_model: Option<String> = args.opt_value_from_str("--scorer_model")?;
+	let scorer_temperature: Option<f32> = args.opt_value_from_str("--scorer_temperature")?;
+	let scorer_max_tokens: Option<u16> = args.opt_value_from_str("--scorer_max_tokens")?;
+	let cli_task = parse_task(&mut args)?;
+	let paths = positionals(args, &COMMAND)?;
+	if paths.is_empty() {
+		anyhow::bail!("No log to rescore, see `eval --help`");
+	}
+	let mut files = vec![];
+	for path in &paths {
+		log_files(Path::new(path), &mut files)?;
+	}
+
+	for file in &files {
+		let manifest = log::Manifest::find(file)?;
+		let (task, task_config, task_options) = task_of(file, manifest.as_ref(), &cli_task)?;
+		let (name, mut task) = load_task(&mut registry, &task, task_config.as_deref(), &task_options)?;
+		let logged_scorer: Option<ModelConfig> = manifest.as_ref().map(|manifest| serde_json::from_value(manifest.options["scorer"].clone())).transpose()?;
+		let scorer = backend.role_config(scorer_model.clone(), scorer_temperature, scorer_max_tokens, logged_scorer.as_ref())?;
+		scorer.context_policy.check(task_options.chat_prompts)?;
+		let seed = manifest.as_ref().and_then(|manifest| manifest.options["seed"].as_u64()).unwrap_or(0);
+
+		let mut logs = log::load(file)?;
+		println!("{} ({}, scorer {})", file.display(), name, scorer.model);
+		for entry in &mut logs {
+			let idx = usize::try_from(entry.idx)?;
+			let mut infos = vec![];
+			for y in &entry.ys {
+				infos.push(task.test_output(idx, y, &scorer).await?);
+			}
+			let old = entry.infos.iter().map(|info| info.r).collect::<Vec<_>>();
+			println!("  {:>5}  logged {:?}  rescored {:?}", entry.idx, old, infos.iter().map(|info| info.r).collect::<Vec<_>>());
+			entry.infos = infos;
+		}
+		let metrics = Metrics::of(&name, &logs, seed);
+		println!("{}", metrics);
+		if dry_run {
+			continue;
+		}
+		let backup = backup_path(file);
+		if !backup.exists() {
+			std::fs::copy(file, &backup).map_err(|e| anyhow::anyhow!("Cannot back up {} to {}: {}", file.display(), backup.display(), e))?;
+		}
+		log::save(file, &logs)?;
+		metrics.save(file)?;
+		println!("Wrote {} and {}, the original log is kept as {}", file.display(), metrics::path_for(file).display(), backup.display());
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{log::AllInfo, tasks::TOutput};
+
+	fn puzzle(idx: isize, ys: &[&str], rs: &[f32]) -> AllInfo {
+		AllInfo {
+			idx,
+			ys: ys.iter().map(|y| y.to_string()).collect(),
+			infos: rs.iter().map(|r| TOutput { r: *r, ..TOutput::new() }).collect(),
+			..AllInfo::new()
+		}
+	}
+
+	async fn eval(flags: &[&str], file: &Path) -> anyhow::Result<()> {
+		let mut cli = flags.iter().map(std::ffi::OsString::from).collect::<Vec<_>>();
+		cli.push(file.into());
+		run(pico_args::Arguments::from_vec(cli), TaskRegistry::with_builtins()).await
+	}
+
+	#[tokio::test]
+	async fn rescores_logs_and_their_metrics() {
+		// a log without a manifest is a game24 log by its directory; its scores are stale
+		let dir = std::env::temp_dir().join(format!("eval-{}", std::process::id()));
+		let file = dir.join("game24").join("sample.json");
+		std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+		let stale = vec![
+			puzzle(0, &["Answer: 4 * 6 * 1 * 1 = 24", "Answer: 4 * 6 + 1 + 1 = 24"], &[0.0, 1.0]),
+			puzzle(1, &["Answer: 11 + 11 + 1 = 24"], &[1.0]),
+		];
+		log::save(&file, &stale).unwrap();
+
+		eval(&["--no_cache", "--dry_run"], &file).await.unwrap();
+		assert_eq!(log::load(&file).unwrap()[0].infos[0].r, 0.0);
+		assert!(!metrics::path_for(&file).exists());
+
+		eval(&["--no_cache"], &file).await.unwrap();
+		let logs = log::load(&file).unwrap();
+		let rs = logs.iter().map(|entry| entry.infos.iter().map(|info| (info.r, info.correct)).collect::<Vec<_>>()).collect::<Vec<_>>();
+		assert_eq!(rs, [vec![(1.0, Some(true)), (0.0, Some(false))], vec![(0.0, Some(false))]]);
+
+		let metrics: Metrics = serde_json::from_str(&std::fs::read_to_string(metrics::path_for(&file)).unwrap()).unwrap();
+		assert_eq!((metrics.task.as_str(), metrics.n), ("game24", 2));
+		assert_eq!(metrics.any_correct.unwrap().value, 0.5);
+		assert_eq!(metrics.mean_of_averages.value, 0.25);
+		assert_eq!(metrics.puzzles.iter().map(|p| p.any_correct).collect::<Vec<_>>(), [Some(true), Some(false)]);
+		// the first rescoring keeps the original log
+		assert_eq!(log::load(&backup_path(&file)).unwrap()[1].infos[0].r, 1.0);
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
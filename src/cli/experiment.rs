@@ -0,0 +1,162 @@
+use std::ffi::OsString;
+
+/// One run of an experiment grid: the flags from the config, and which swept values picked it.
+#[derive(Debug, Clone)]
+pub(super) struct Point {
+	flags: Vec<(String, Option<String>)>,
+	pub(super) swept: Vec<(String, String)>,
+}
+
+impl Point {
+	/// `cli` with every flag of the point that it does not set itself; the command line wins over the config,
+	/// except for swept parameters, which it may not set.
+	pub(super) fn args(&self, cli: &[OsString]) -> anyhow::Result<Vec<OsString>> {
+		let given = |key: &str| {
+			let flag = format!("--{key}");
+			cli.iter().filter_map(|arg| arg.to_str()).any(|arg| arg == flag || arg.starts_with(&format!("{flag}=")))
+		};
+		if let Some((key, _)) = self.swept.iter().find(|(key, _)| given(key)) {
+			anyhow::bail!("--{} is swept by the experiment config and cannot be given on the command line", key);
+		}
+		let mut args = cli.to_vec();
+		for (key, value) in self.flags.iter().filter(|(key, _)| !given(key)) {
+			args.push(format!("--{key}").into());
+			args.extend(value.iter().map(OsString::from));
+		}
+		Ok(args)
+	}
+}
+
+/// `key=value` pairs of the swept parameters, for logs and the summary table.
+pub(super) fn label(swept: &[(String, String)]) -> String {
+	if swept.is_empty() {
+		return "-".to_string();
+	}
+	swept.iter().map(|(key, value)| format!("{key}={value}")).collect::<Vec<_>>().join(" ")
+}
+
+/// Reads a TOML or JSON experiment: keys are `run` flags without their dashes.
+pub(super) fn load(path: &str) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+	let content = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read experiment config {}: {}", path, e))?;
+	let config: serde_json::Value = match std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
+		Some("toml") => toml::from_str(&content)?,
+		Some("json") => serde_json::from_str(&content)?,
+		ext => anyhow::bail!("Invalid experiment config extension: {:?}, expected toml or json", ext),
+	};
+	match config {
+		serde_json::Value::Object(config) => Ok(config),
+		_ => anyhow::bail!("Experiment config {} must be a table of flags", path),
+	}
+}
+
+/// The value a flag takes on the command line; `None` for a switch that is on.
+fn flag_value(key: &str, value: &serde_json::Value) -> anyhow::Result<Option<Option<String>>> {
+	Ok(match value {
+		serde_json::Value::Bool(true) => Some(None),
+		serde_json::Value::Bool(false) | serde_json::Value::Null => None,
+		serde_json::Value::String(s) => Some(Some(s.clone())),
+		serde_json::Value::Number(n) => Some(Some(n.to_string())),
+		value => anyhow::bail!("Invalid value of {} in experiment config: {}", key, value),
+	})
+}
+
+/// Expands every array value into a sweep; the grid is the cartesian product of the sweeps.
+pub(super) fn grid(config: &serde_json::Map<String, serde_json::Value>) -> anyhow::Result<Vec<Point>> {
+	let mut points = vec![Point { flags: vec![], swept: vec![] }];
+	for (key, value) in config {
+		if key == "config" {
+			anyhow::bail!("Experiment configs cannot include other configs");
+		}
+		let serde_json::Value::Array(values) = value else {
+			if let Some(flag) = flag_value(key, value)? {
+				points.iter_mut().for_each(|point| point.flags.push((key.clone(), flag.clone())));
+			}
+			continue;
+		};
+		if values.is_empty() {
+			anyhow::bail!("Sweep over {} has no values", key);
+		}
+		if let Some(value) = values.iter().enumerate().find_map(|(i, value)| values[..i].contains(value).then_some(value)) {
+			anyhow::bail!("Sweep over {} repeats {}", key, value);
+		}
+		let mut swept = vec![];
+		for point in &points {
+			for value in values {
+				let mut point = point.clone();
+				if let Some(flag) = flag_value(key, value)? {
+					point.flags.push((key.clone(), flag));
+				}
+				point.swept.push((key.clone(), value.as_str().map_or_else(|| value.to_string(), str::to_string)));
+				swept.push(point);
+			}
+		}
+		points = swept;
+	}
+	Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config(toml: &str) -> serde_json::Map<String, serde_json::Value> {
+		match toml::from_str(toml).unwrap() {
+			serde_json::Value::Object(config) => config,
+			_ => unreachable!(),
+		}
+	}
+
+	fn strings(args: &[OsString]) -> Vec<&str> {
+		args.iter().map(|arg| arg.to_str().unwrap()).collect()
+	}
+
+	#[test]
+	fn sweeps_the_product_of_list_keys() {
+		let points = grid(&config("task = \"game24\"\nn_select_sample = [1, 3, 5]\nmethod_evaluate = [\"value\", \"vote\"]")).unwrap();
+		assert_eq!(points.len(), 6);
+		// keys vary in order, the first one slowest
+		let labels = points.iter().map(|point| label(&point.swept)).collect::<Vec<_>>();
+		assert_eq!(
+			labels,
+			[
+				"method_evaluate=value n_select_sample=1",
+				"method_evaluate=value n_select_sample=3",
+				"method_evaluate=value n_select_sample=5",
+				"method_evaluate=vote n_select_sample=1",
+				"method_evaluate=vote n_select_sample=3",
+				"method_evaluate=vote n_select_sample=5",
+			]
+		);
+		assert_eq!(strings(&points[4].args(&[]).unwrap()), ["--method_evaluate", "vote", "--n_select_sample", "3", "--task", "game24"]);
+	}
+
+	#[test]
+	fn turns_booleans_into_switches() {
+		let points = grid(&config("naive_run = true\nchat_prompts = false\ncache = [false, true]")).unwrap();
+		assert_eq!(points.len(), 2);
+		assert_eq!(strings(&points[0].args(&[]).unwrap()), ["--naive_run"]);
+		assert_eq!(strings(&points[1].args(&[]).unwrap()), ["--cache", "--naive_run"]);
+		assert_eq!(label(&points[1].swept), "cache=true");
+	}
+
+	#[test]
+	fn lets_the_command_line_override_fixed_keys() {
+		let points = grid(&config("temperature = 0.7\nseed = 1\nn_generate_sample = [1, 2]")).unwrap();
+		let cli = ["--temperature=0.2", "--seed", "9"].map(OsString::from);
+		assert_eq!(strings(&points[0].args(&cli).unwrap()), ["--temperature=0.2", "--seed", "9", "--n_generate_sample", "1"]);
+
+		let cli = ["--n_generate_sample", "3"].map(OsString::from);
+		let err = points[0].args(&cli).err().unwrap();
+		assert_eq!(err.to_string(), "--n_generate_sample is swept by the experiment config and cannot be given on the command line");
+	}
+
+	#[test]
+	fn rejects_invalid_values() {
+		let error = |toml: &str| grid(&config(toml)).err().unwrap().to_string();
+		assert_eq!(error("task_param = { target = 10 }"), "Invalid value of task_param in experiment config: {\"target\":10}");
+		assert_eq!(error("seed = [1, [2]]"), "Invalid value of seed in experiment config: [2]");
+		assert_eq!(error("seed = []"), "Sweep over seed has no values");
+		assert_eq!(error("seed = [1, 1]"), "Sweep over seed repeats 1");
+		assert_eq!(error("config = \"other.toml\""), "Experiment configs cannot include other configs");
+	}
+}
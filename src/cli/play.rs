@@ -0,0 +1,187 @@
+use super::{parse_backend, parse_task, positionals, wants_help, Command, HELP_FLAG, MODEL_FLAGS, TASK_FLAGS};
+use crate::{
+	models::ModelConfig,
+	tasks::{MiniCrossword, ToTTask},
+};
+use regex::Regex;
+use std::{
+	io::{BufRead, Write},
+	sync::LazyLock,
+};
+
+pub(super) const COMMAND: Command = Command {
+	name: "play",
+	args: "[IDX]",
+	about: "solve a crossword by hand: place words, undo them and ask the model for proposals",
+	flags: &[TASK_FLAGS, MODEL_FLAGS, HELP_FLAG],
+};
+
+const COMMANDS: &str = "\
+h1. apple    place a word, h<n> across or v<n> down; `_` blanks a cell
+undo         take back the last move
+reset        clear the board
+suggest [K]  ask the generator for its K best proposals, all by default
+puzzle N     switch to puzzle N
+board        show the board again
+help         show this help
+quit         leave, as does end of input
+";
+
+/// One puzzle being played: its index, clues and the moves so far.
+struct Game {
+	idx: usize,
+	x: String,
+	moves: Vec<String>,
+}
+
+impl Game {
+	fn new(task: &mut MiniCrossword, idx: usize) -> anyhow::Result<Game> {
+		if idx >= task.len() {
+			anyhow::bail!("Puzzle {} is out of range, the dataset has {} puzzles", idx, task.len());
+		}
+		Ok(Game { idx, x: task.get_input(idx)?, moves: vec![] })
+	}
+
+	/// The moves as a search candidate, so the task's proposals can extend it.
+	fn y(&self) -> String {
+		self.moves.iter().map(|action| format!("{action}\n")).collect()
+	}
+
+	fn show(&self, task: &mut MiniCrossword) -> anyhow::Result<()> {
+		let position = task.replay(self.idx, &self.moves)?;
+		let rewards = position.rewards;
+		println!("Puzzle {}, {} moves\n{}", self.idx, self.moves.len(), position.render);
+		println!("letters {:.2}, words {:.2}{}", rewards.r_letter, rewards.r_word, if rewards.r_game { ", solved!" } else { "" });
+		Ok(())
+	}
+}
+
+static MOVE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([hvHV]\d+)\.?\s+(\S+)$").unwrap());
+
+/// `h1 apple` and `H1. Apple` both become the `h1. apple` action the environment takes.
+fn parse_move(line: &str) -> Option<String> {
+	MOVE.captures(line).map(|c| format!("{}. {}", c[1].to_lowercase(), &c[2]))
+}
+
+pub(super) async fn run(mut args: pico_args::Arguments) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let backend = parse_backend(&mut args)?;
+	let generator: ModelConfig = backend.model_config(&mut args, ["--generator_model", "--generator_temperature", "--generator_max_tokens"])?;
+	let (task, task_config, task_options) = parse_task(&mut args)?;
+	if !matches!(task.as_str(), "" | "crosswords") || task_config.is_some() {
+		anyhow::bail!("play only supports the crosswords task");
+	}
+	let idx = match positionals(args, &COMMAND)?.as_slice() {
+		[] => 0,
+		[idx] => idx.parse().map_err(|_| anyhow::anyhow!("Invalid puzzle index: {}", idx))?,
+		_ => anyhow::bail!("play takes at most one puzzle index, see `play --help`"),
+	};
+
+	let mut task = MiniCrossword::new(&task_options)?;
+	let mut game = Game::new(&mut task, idx)?;
+	print!("{}\n{}", COMMANDS, game.x);
+	game.show(&mut task)?;
+	let mut lines = std::io::stdin().lock().lines();
+	loop {
+		print!("> ");
+		std::io::stdout().flush()?;
+		let Some(line) = lines.next().transpose()? else {
+			println!();
+			return Ok(());
+		};
+		let mut words = line.split_whitespace();
+		match (words.next(), words.next()) {
+			(None, _) => {}
+			(Some("quit" | "exit"), _) => return Ok(()),
+			(Some("help"), _) => print!("{}", COMMANDS),
+			(Some("board"), _) => game.show(&mut task)?,
+			(Some("undo"), _) => match game.moves.pop() {
+				Some(action) => {
+					println!("Took back {}", action);
+					game.show(&mut task)?;
+				}
+				None => println!("Nothing to undo"),
+			},
+			(Some("reset"), _) => {
+				game.moves.clear();
+				game.show(&mut task)?;
+			}
+			(Some("puzzle"), Some(n)) => match n.parse().map_err(anyhow::Error::from).and_then(|idx| Game::new(&mut task, idx)) {
+				Ok(new_game) => {
+					game = new_game;
+					print!("{}", game.x);
+					game.show(&mut task)?;
+				}
+				Err(e) => println!("Cannot open puzzle {}: {}", n, e),
+			},
+			(Some("puzzle"), None) => println!("Usage: puzzle N"),
+			(Some("suggest"), k) => {
+				let k = match k.map(str::parse::<usize>).transpose() {
+					Ok(k) => k,
+					Err(_) => {
+						println!("Usage: suggest [K]");
+						continue;
+					}
+				};
+				let y = game.y();
+				match task.get_proposals(&game.x, &y, &generator, k).await {
+					Ok(proposals) if proposals.is_empty() => println!("No proposals fit the board"),
+					Ok(proposals) => proposals.iter().enumerate().for_each(|(i, proposal)| println!("{:>3}. {}", i + 1, proposal[y.len()..].trim_end())),
+					Err(e) => println!("No proposals: {}", e),
+				}
+			}
+			_ => match parse_move(line.trim()) {
+				Some(action) => {
+					game.moves.push(action);
+					if let Err(e) = task.replay(game.idx, &game.moves) {
+						game.moves.pop();
+						println!("{}", e);
+						continue;
+					}
+					game.show(&mut task)?;
+				}
+				None => println!("Unknown command {:?}, see `help`", line.trim()),
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tasks::TaskOptions;
+
+	#[test]
+	fn parses_moves() {
+		assert_eq!(parse_move("h1. agend").as_deref(), Some("h1. agend"));
+		assert_eq!(parse_move("H1 AGEND").as_deref(), Some("h1. AGEND"));
+		assert_eq!(parse_move("v12.   m_a__").as_deref(), Some("v12. m_a__"));
+		for line in ["h1.agend", "x1. agend", "h. agend", "h1", "h1. two words", "undo"] {
+			assert_eq!(parse_move(line), None, "{line}");
+		}
+	}
+
+	#[test]
+	fn undo_restores_the_board() {
+		let mut task = MiniCrossword::new(&TaskOptions::default()).unwrap();
+		let mut game = Game::new(&mut task, 0).unwrap();
+		let empty = task.replay(game.idx, &game.moves).unwrap();
+		game.moves.push(parse_move("h1 agend").unwrap());
+		let one = task.replay(game.idx, &game.moves).unwrap();
+		game.moves.push(parse_move("v1. amass").unwrap());
+		let two = task.replay(game.idx, &game.moves).unwrap();
+		assert_ne!(one.render, two.render);
+		assert!(two.rewards.r_letter > one.rewards.r_letter);
+		assert_eq!(game.y(), "h1. agend\nv1. amass\n");
+
+		game.moves.pop();
+		let undone = task.replay(game.idx, &game.moves).unwrap();
+		assert_eq!((undone.render, undone.rewards.r_letter), (one.render, one.rewards.r_letter));
+		game.moves.pop();
+		assert_eq!(task.replay(game.idx, &game.moves).unwrap().render, empty.render);
+		let len = task.len();
+		assert!(Game::new(&mut task, len).is_err());
+	}
+}
@@ -0,0 +1,71 @@
+use super::{flag, positionals, wants_help, Command, Flag, HELP_FLAG};
+use crate::log::{self, AllInfo};
+use std::path::Path;
+
+const RENDER_FLAGS: &[Flag] = &[
+	flag("--idx", "N", "only the puzzle with this index"),
+	flag("--width", "N", "cut thoughts to this many characters, 100 by default"),
+];
+
+pub(super) const COMMAND: Command = Command {
+	name: "render",
+	args: "<LOG>",
+	about: "draw the search tree of each puzzle in a run log",
+	flags: &[RENDER_FLAGS, HELP_FLAG],
+};
+
+fn cut(text: &str, width: usize) -> String {
+	let text = text.trim().replace('\n', " | ");
+	match text.char_indices().nth(width) {
+		Some((end, _)) => format!("{}...", &text[..end]),
+		None => text,
+	}
+}
+
+/// One block per step: each frontier node with the candidates it expanded into, showing only the
+/// thought a candidate adds to its parent. Kept candidates are starred.
+fn render(entry: &AllInfo, width: usize) -> String {
+	let mut s = format!("Puzzle {}: {}\n", entry.idx, cut(entry.x(), width));
+	for step in &entry.steps {
+		s.push_str(&format!("step {}\n", step.step + 1));
+		for (p, parent) in step.ys.iter().enumerate() {
+			s.push_str(&format!("  [{}] {}\n", p, if parent.is_empty() { "(root)".to_string() } else { cut(parent.lines().last().unwrap_or(""), width) }));
+			let children = step.new_ys.iter().enumerate().filter(|(_, y)| y.starts_with(parent.as_str())).collect::<Vec<_>>();
+			for (n, (i, y)) in children.iter().enumerate() {
+				let branch = if n + 1 == children.len() { "└─" } else { "├─" };
+				let kept = if step.select_new_ys.contains(y) { "*" } else { " " };
+				let value = step.values.get(*i).map_or(" ".repeat(9), |value| format!("{value:>8.3} "));
+				s.push_str(&format!("      {} {} {}{}\n", branch, kept, value, cut(&y[parent.len()..], width)));
+			}
+		}
+	}
+	if let Some(depth) = entry.solved_depth {
+		s.push_str(&format!("solved at depth {}\n", depth));
+	}
+	for (y, info) in entry.ys.iter().zip(&entry.infos) {
+		s.push_str(&format!("final r={:.3}: {}\n", info.r, cut(y, width)));
+	}
+	s
+}
+
+pub(super) fn run(mut args: pico_args::Arguments) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let idx: Option<isize> = args.opt_value_from_str("--idx")?;
+	let width = args.opt_value_from_str("--width")?.unwrap_or(100);
+	let paths = positionals(args, &COMMAND)?;
+	let [path] = paths.as_slice() else {
+		anyhow::bail!("Expected one log to render, see `render --help`");
+	};
+
+	let logs = log::load(Path::new(path))?;
+	let entries = logs.iter().filter(|entry| idx.is_none_or(|idx| entry.idx == idx)).collect::<Vec<_>>();
+	if entries.is_empty() {
+		anyhow::bail!("No puzzle {:?} in {}", idx, path);
+	}
+	for entry in entries {
+		println!("{}", render(entry, width));
+	}
+	Ok(())
+}
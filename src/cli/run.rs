@@ -0,0 +1,472 @@
+use super::{experiment, finish, flag, load_task, parse_opts, wants_help, Command, Flag, Opts, HELP_FLAG, MODEL_FLAGS, SEARCH_FLAGS, TASK_FLAGS};
+use crate::{
+	cache,
+	log::{self, AllInfo, InfoData},
+	metrics::{Metrics, PuzzleScore},
+	models::gpt_usage,
+	select::Selector,
+	tasks::{TaskRegistry, ToTTask},
+};
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+	ffi::OsString,
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+const SELECTION_FLAGS: &[Flag] = &[
+	flag("--task_start_index", "N", "first puzzle, the task's default range by default"),
+	flag("--task_end_index", "N", "puzzle after the last one"),
+	flag("--indices", "LIST", "puzzles such as 3,7,10-20 (ranges include both ends)"),
+	flag("--ids", "LIST", "comma separated puzzle ids, in the order to run them"),
+	flag("--sample", "N", "a random subset of N puzzles, drawn with --seed"),
+];
+
+const OUTPUT_FLAGS: &[Flag] = &[
+	flag("--log_dir", "DIR", "where runs are logged, as <dir>/<task>/<run name>/, ./logs by default"),
+	flag("--run_name", "NAME", "run directory name, which must be new; by default the search parameters and a hash of every setting, with -2, -3... for repeats"),
+];
+
+const CONFIG_FLAGS: &[Flag] = &[flag(
+	"--config",
+	"FILE",
+	"TOML or JSON file of run flags without their dashes; array values are swept as a grid. Flags given on the command line win",
+)];
+
+pub(super) const COMMAND: Command = Command {
+	name: "run",
+	args: "",
+	about: "run the search over a range of puzzles and log every step",
+	flags: &[CONFIG_FLAGS, MODEL_FLAGS, TASK_FLAGS, SELECTION_FLAGS, SEARCH_FLAGS, OUTPUT_FLAGS, HELP_FLAG],
+};
+
+/// Parses `3,7,10-20` into puzzle indices; ranges include both ends.
+fn parse_indices(spec: &str) -> anyhow::Result<Vec<usize>> {
+	let mut indices = vec![];
+	for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+		match part.split_once('-') {
+			Some((from, to)) => {
+				let (from, to): (usize, usize) = (from.trim().parse()?, to.trim().parse()?);
+				if from > to {
+					anyhow::bail!("Invalid index range: {}", part);
+				}
+				indices.extend(from..=to);
+			}
+			None => indices.push(part.parse().map_err(|_| anyhow::anyhow!("Invalid index: {:?}", part))?),
+		}
+	}
+	if indices.is_empty() {
+		anyhow::bail!("--indices selects no puzzles");
+	}
+	Ok(indices)
+}
+
+/// The puzzles to run, checked against the dataset size before anything is sent to a model: `--indices`, or
+/// the `--task_start_index`/`--task_end_index` range, each end defaulting to the task's own range.
+fn select_puzzles(options: &Opts, task: &dyn ToTTask) -> anyhow::Result<Vec<usize>> {
+	let len = task.len();
+	if len == 0 {
+		anyhow::bail!("The dataset of task {} is empty", options.task);
+	}
+	if let Some(indices) = &options.indices {
+		if let Some(idx) = indices.iter().find(|idx| **idx >= len) {
+			anyhow::bail!("Index {} is out of range, the dataset has {} puzzles (0-{})", idx, len, len - 1);
+		}
+		return Ok(indices.clone());
+	}
+
+	// an id list or random subset is already the whole run
+	let default = if options.task_options.dataset.selects() { 0..len } else { task.default_range() };
+	let start = options.task_start_index.map_or(Ok(default.start), usize::try_from)?;
+	let end = options.task_end_index.map_or(Ok(default.end), usize::try_from)?;
+	if start >= end || end > len {
+		anyhow::bail!("Invalid puzzle range {}..{}, the dataset has {} puzzles", start, end, len);
+	}
+	Ok((start..end).collect())
+}
+
+/// Searches from input `x` and returns the final candidates, best first, with the steps taken.
+/// `idx` is the puzzle `x` came from, if any; it lets the task's verifier end the search early.
+pub(super) async fn search(options: &Opts, task: &mut dyn ToTTask, x: &str, idx: Option<usize>, rng: &mut StdRng, verbose: bool) -> anyhow::Result<(Vec<String>, AllInfo)> {
+	let mut all_info = AllInfo::new();
+	if options.naive_run {
+		let ys = task
+			.get_samples(x, "", &options.generator, options.n_generate_sample, options.prompt_sample.as_deref().unwrap_or(""), None)
+			.await?;
+		return Ok((ys, all_info));
+	}
+
+	let mut ys = vec![String::new()];
+	for step in 0..task.get_steps() {
+		let new_ys = match options.method_generate.as_deref() {
+			Some("sample") => {
+				let mut new_ys = Vec::new();
+				for y in &ys {
+					let new_y = task
+						.get_samples(
+							x,
+							y,
+							&options.generator,
+							options.n_generate_sample,
+							options.prompt_sample.as_deref().unwrap_or(""),
+							task.get_stop(step as usize),
+						)
+						.await?;
+					new_ys.extend(new_y);
+				}
+				new_ys
+			}
+			Some("propose") => {
+				let mut new_ys = Vec::new();
+				for y in &ys {
+					let new_y = task.get_proposals(x, y, &options.generator, options.proposals_per_node).await?;
+					new_ys.extend(new_y);
+				}
+				new_ys
+			}
+			method => anyhow::bail!("Invalid method_generate: {:?} (expected sample or propose)", method),
+		};
+		let mut info = InfoData {
+			step: step.try_into()?,
+			x: x.to_string(),
+			ys: ys.clone(),
+			..Default::default()
+		};
+		let mut solved = None;
+		if let Some(idx) = idx {
+			for y in &new_ys {
+				if task.is_solved(idx, y).await {
+					solved = Some(y.clone());
+					break;
+				}
+			}
+		}
+		if let Some(solved) = solved {
+			if verbose {
+				println!("Solved at depth {}", step + 1);
+			}
+			info.new_ys = new_ys;
+			info.select_new_ys = vec![solved.clone()];
+			all_info.steps.push(info);
+			all_info.solved_depth = Some(step + 1);
+			ys = vec![solved];
+			break;
+		}
+		let (values, votes) = match options.method_evaluate.as_deref() {
+			Some("vote") => task.get_votes(x, &new_ys, &options.evaluator, options.n_evaluate_sample).await?,
+			Some("value") => (task.get_values(x, &new_ys, &options.evaluator, options.n_evaluate_sample, None).await?, vec![]),
+			ev => anyhow::bail!("Invalid method_evaluate: {:?} (expected value or vote)", ev),
+		};
+		if verbose {
+			println!("Values::: {:?}", values);
+		}
+		let Some(selector) = &options.method_select else {
+			anyhow::bail!("Missing --method_select");
+		};
+		let select_ids = selector.select(&values, options.n_select_sample as usize, rng);
+		let select_new_ys = select_ids.iter().map(|id| new_ys[*id].clone()).collect::<Vec<_>>();
+
+		info.new_ys = new_ys;
+		info.values = values;
+		info.votes = votes;
+		info.select_new_ys = select_new_ys.clone();
+		all_info.steps.push(info);
+		ys = select_new_ys;
+	}
+	Ok((ys, all_info))
+}
+
+/// What one run of a grid reports in the summary table.
+struct Summary {
+	label: String,
+	metrics: Metrics,
+	file: String,
+}
+
+/// Runs the experiment on the command line, or every run of the `--config` grid followed by a table comparing them.
+pub(super) async fn run(mut args: pico_args::Arguments, mut registry: TaskRegistry) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let config: Option<String> = args.opt_value_from_str("--config")?;
+	let cli = args.finish();
+	let Some(config) = config else {
+		run_experiment(cli, &mut registry, &[]).await?;
+		return Ok(());
+	};
+
+	let mut config = experiment::load(&config)?;
+	// runs of a grid share one seed unless it is swept, so they differ only in the swept parameters
+	config.entry("seed").or_insert_with(|| rand::random::<u64>().to_string().into());
+	let points = experiment::grid(&config)?;
+	let args = points.iter().map(|point| point.args(&cli)).collect::<anyhow::Result<Vec<_>>>()?;
+	println!("Running a grid of {} experiments", points.len());
+	let mut summaries = vec![];
+	for (i, (point, args)) in points.iter().zip(args).enumerate() {
+		println!("Experiment {}/{}: {}", i + 1, points.len(), experiment::label(&point.swept));
+		summaries.push(run_experiment(args, &mut registry, &point.swept).await?);
+	}
+
+	let width = summaries.iter().map(|summary| summary.label.len()).max().unwrap_or(0).max("experiment".len());
+	println!("\n{:width$}  {:>5}  {:>21}  {:>21}  {:>21}  {:>9}  {:>9}  log", "experiment", "n", "mean of averages", "any correct", "best-of-k", "tokens", "cost");
+	for Summary { label, metrics, file } in summaries {
+		let usage = metrics.usage;
+		println!(
+			"{:width$}  {:>5}  {:>21}  {:>21}  {:>21}  {:>9}  {:>9.4}  {}",
+			label,
+			metrics.n,
+			metrics.mean_of_averages.to_string(),
+			metrics.any_correct_text(),
+			metrics.best_of_k.to_string(),
+			usage.completion_tokens + usage.prompt_tokens,
+			usage.cost,
+			file
+		);
+	}
+	Ok(())
+}
+
+/// The default run name: every search parameter, then a hash of all settings (seed included) so
+/// runs that differ elsewhere, or only in their seed, do not share a directory.
+fn run_name(options: &Opts, start: usize, end: usize) -> anyhow::Result<String> {
+	let method = |method: Option<&str>, n: isize| format!("{}{}", method.unwrap_or("none"), n);
+	let search = if options.naive_run {
+		method(Some("naive"), options.n_generate_sample)
+	} else {
+		[
+			method(options.method_generate.as_deref(), options.n_generate_sample),
+			method(options.method_evaluate.as_deref(), options.n_evaluate_sample),
+			method(options.method_select.as_ref().map(Selector::name), options.n_select_sample),
+		]
+		.join("-")
+	};
+	Ok(format!(
+		"{}_{}_{}_{}_start{}_end{}_{:08x}",
+		options.backend.as_deref().unwrap_or("gpt-4"),
+		options.temperature,
+		options.prompt_sample.as_deref().unwrap_or("none"),
+		search,
+		start,
+		end,
+		cache::hash(&serde_json::to_string(options)?) as u32
+	))
+}
+
+/// `name`, or `name-2`, `name-3` and so on when an identical run is already logged in `task_dir`.
+fn unused_run_name(task_dir: &Path, name: String) -> String {
+	let logged = |name: &str| task_dir.join(name).join(log::LOG_FILE).exists();
+	if !logged(&name) {
+		return name;
+	}
+	(2..).map(|n| format!("{name}-{n}")).find(|name| !logged(name)).expect("some counter is free")
+}
+
+/// Runs one experiment: every selected puzzle is searched, scored and appended to
+/// `<log_dir>/<task>/<run name>/log.json`, with the settings in `run.json` next to it.
+/// `swept` names the grid parameters that set this run apart.
+async fn run_experiment(cli: Vec<OsString>, registry: &mut TaskRegistry, swept: &[(String, String)]) -> anyhow::Result<Summary> {
+	let mut args = pico_args::Arguments::from_vec(cli.clone());
+	let mut options = parse_opts(&mut args)?;
+	let ids: Option<String> = args.opt_value_from_str("--ids")?;
+	options.task_options.dataset.ids = ids.map(|ids| ids.split(',').map(|id| id.trim().to_string()).filter(|id| !id.is_empty()).collect()).unwrap_or_default();
+	options.task_options.dataset.sample = args.opt_value_from_str("--sample")?;
+	options.task_options.dataset.seed = options.seed;
+	options.task_start_index = args.opt_value_from_str("--task_start_index")?;
+	options.task_end_index = args.opt_value_from_str("--task_end_index")?;
+	let indices: Option<String> = args.opt_value_from_str("--indices")?;
+	options.indices = indices.as_deref().map(parse_indices).transpose()?;
+	if options.indices.is_some() && (options.task_start_index.is_some() || options.task_end_index.is_some()) {
+		anyhow::bail!("--indices cannot be combined with --task_start_index/--task_end_index");
+	}
+	if let Some(log_dir) = args.opt_value_from_str("--log_dir")? {
+		options.log_dir = log_dir;
+	}
+	options.run_name = args.opt_value_from_str("--run_name")?;
+	finish(args, &COMMAND)?;
+
+	let usage_before = gpt_usage().await;
+	let (name, mut task) = load_task(registry, &options.task, options.task_config.as_deref(), &options.task_options)?;
+	options.task = name;
+	let puzzles = select_puzzles(&options, task.as_ref())?;
+	let (start, end) = (puzzles[0], puzzles[puzzles.len() - 1] + 1);
+	// the backend and every other setting go to the run manifest; the seed is shown to replay an unseeded run
+	println!("Running {} puzzles of {} with seed {}", puzzles.len(), task.len(), options.seed);
+
+	let mut logs = vec![];
+	let run_name = match &options.run_name {
+		// every run of a grid would otherwise share the given name
+		Some(name) => swept.iter().fold(name.clone(), |name, (key, value)| format!("{}_{}-{}", name, key, value.replace(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '-', "_"))),
+		None => run_name(&options, start, end)?,
+	};
+	if run_name.is_empty() || run_name.contains(['/', '\\']) || run_name.starts_with('.') {
+		anyhow::bail!("Invalid run name {:?}, it names a directory inside {}", run_name, options.log_dir.join(&options.task).display());
+	}
+	let task_dir = options.log_dir.join(&options.task);
+	let run_name = match &options.run_name {
+		Some(_) if task_dir.join(&run_name).join(log::LOG_FILE).exists() => {
+			anyhow::bail!("{} already exists, pick another --run_name or remove it", task_dir.join(&run_name).join(log::LOG_FILE).display())
+		}
+		Some(_) => run_name,
+		None => unused_run_name(&task_dir, run_name),
+	};
+	let dir = task_dir.join(&run_name);
+	let file = dir.join(log::LOG_FILE);
+	std::fs::create_dir_all(&dir)?;
+	println!("Logging to {}", file.display());
+	log::Manifest {
+		run_name,
+		task: options.task.clone(),
+		started: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+		args: cli.iter().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+		swept: swept.to_vec(),
+		options: serde_json::to_value(&options)?,
+		puzzles: puzzles.clone(),
+	}
+	.save(&dir)?;
+
+	for i in puzzles {
+		// each puzzle gets its own stream so a single index replays the same way as in a full run
+		let mut rng = StdRng::seed_from_u64(options.seed.wrapping_add(i as u64));
+		let x = task.get_input(i)?;
+		let outcome = async {
+			let (ys, mut info) = search(&options, task.as_mut(), &x, Some(i), &mut rng, true).await?;
+			for y in &ys {
+				info.infos.push(task.test_output(i, y, &options.scorer).await?);
+			}
+			info.ys = ys;
+			anyhow::Ok(info)
+		};
+		// a failed request loses this puzzle, not the puzzles logged before or after it
+		let mut info = outcome.await.unwrap_or_else(|e| {
+			println!("Puzzle {} failed: {:#}", i, e);
+			AllInfo {
+				error: Some(format!("{:#}", e)),
+				..AllInfo::new()
+			}
+		});
+		info.idx = i as isize;
+		info.id = task.get_id(i);
+		info.seed = options.seed;
+		// usage is counted across the process; a run of a grid only logs its own
+		let usage = gpt_usage().await;
+		info.usage_so_far = (usage.0 - usage_before.0, usage.1 - usage_before.1, usage.2 - usage_before.2);
+		logs.push(info);
+		log::save(&file, &logs)?;
+
+		let score = PuzzleScore::of(&options.task, logs.last().unwrap());
+		let any_correct = score.any_correct.map_or_else(|| "n/a".to_string(), |correct| correct.to_string());
+		println!("Puzzle {}: mean r {:.3}, any correct {}, best-of-k r {:.3}", i, score.mean, any_correct, score.best_of_k);
+	}
+
+	let metrics = Metrics::of(&options.task, &logs, options.seed);
+	println!("{}", metrics);
+	metrics.save(&file)?;
+	Ok(Summary {
+		label: experiment::label(swept),
+		metrics,
+		file: file.display().to_string(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tasks::{Game24, TaskOptions};
+
+	fn opts(args: &[&str]) -> anyhow::Result<Opts> {
+		parse_opts(&mut pico_args::Arguments::from_vec(args.iter().map(OsString::from).collect()))
+	}
+
+	#[test]
+	fn parses_indices() {
+		assert_eq!(parse_indices("3").unwrap(), [3]);
+		assert_eq!(parse_indices("10-13").unwrap(), [10, 11, 12, 13]);
+		assert_eq!(parse_indices("3, 7,10-12,5-5,").unwrap(), [3, 7, 10, 11, 12, 5]);
+		assert_eq!(parse_indices(" , ").err().unwrap().to_string(), "--indices selects no puzzles");
+		assert_eq!(parse_indices("12-10").err().unwrap().to_string(), "Invalid index range: 12-10");
+		assert_eq!(parse_indices("3,x").err().unwrap().to_string(), "Invalid index: \"x\"");
+		assert!(parse_indices("-3").is_err());
+	}
+
+	#[test]
+	fn selects_puzzles_within_the_dataset() {
+		let task = Game24::new(&TaskOptions::default()).unwrap();
+		let mut options = opts(&["--task", "game24"]).unwrap();
+		assert_eq!(select_puzzles(&options, &task).unwrap(), (900..1000).collect::<Vec<_>>());
+
+		options.task_start_index = Some(5);
+		options.task_end_index = Some(8);
+		assert_eq!(select_puzzles(&options, &task).unwrap(), [5, 6, 7]);
+		options.task_start_index = Some(8);
+		options.task_end_index = Some(5);
+		assert_eq!(select_puzzles(&options, &task).err().unwrap().to_string(), "Invalid puzzle range 8..5, the dataset has 1362 puzzles");
+		options.task_start_index = Some(1360);
+		options.task_end_index = Some(1400);
+		assert!(select_puzzles(&options, &task).is_err());
+
+		options.indices = Some(vec![1361, 0, 7]);
+		assert_eq!(select_puzzles(&options, &task).unwrap(), [1361, 0, 7]);
+		options.indices = Some(vec![0, 1362]);
+		assert_eq!(select_puzzles(&options, &task).err().unwrap().to_string(), "Index 1362 is out of range, the dataset has 1362 puzzles (0-1361)");
+	}
+
+	#[test]
+	fn rejects_an_empty_dataset() {
+		let file = std::env::temp_dir().join(format!("run-empty-{}.csv", std::process::id()));
+		std::fs::write(&file, "Rank,Puzzles\n").unwrap();
+		let task_options = TaskOptions {
+			file_path: file.display().to_string(),
+			..Default::default()
+		};
+		let task = Game24::new(&task_options).unwrap();
+		std::fs::remove_file(&file).unwrap();
+		let options = opts(&["--task", "game24"]).unwrap();
+		assert_eq!(select_puzzles(&options, &task).err().unwrap().to_string(), "The dataset of task game24 is empty");
+	}
+
+	#[test]
+	fn rejects_sample_counts_below_one() {
+		for flag in ["--n_generate_sample", "--n_evaluate_sample", "--n_select_sample"] {
+			assert_eq!(opts(&[flag, "0"]).err().unwrap().to_string(), format!("Invalid {flag} 0, expected at least 1"));
+			assert!(opts(&[flag, "-2"]).is_err());
+			assert!(opts(&[flag, "2"]).is_ok());
+		}
+	}
+
+	#[test]
+	fn names_runs_by_their_search_settings() {
+		let name = |args: &str| run_name(&opts(&args.split(' ').collect::<Vec<_>>()).unwrap(), 0, 10).unwrap();
+		let base = name("--method_generate propose --method_evaluate value --method_select greedy --seed 1");
+		assert!(base.starts_with("gpt-4_0.7_none_propose1-value1-greedy1_start0_end10_"), "{base}");
+		assert_eq!(name("--method_generate propose --method_evaluate value --method_select greedy --seed 1"), base);
+
+		let names = [
+			("--method_generate propose --method_evaluate value --method_select greedy --n_select_sample 5 --seed 1", "gpt-4_0.7_none_propose1-value1-greedy5_"),
+			("--method_generate propose --method_evaluate vote --method_select greedy --n_evaluate_sample 3 --seed 1", "gpt-4_0.7_none_propose1-vote3-greedy1_"),
+			("--method_generate sample --method_evaluate value --method_select softmax --n_generate_sample 2 --seed 1", "gpt-4_0.7_none_sample2-value1-softmax1_"),
+			("--method_generate propose --method_evaluate value --method_select greedy --prompt_sample cot --temperature 0.2 --seed 1", "gpt-4_0.2_cot_propose1-value1-greedy1_"),
+			("--naive_run --n_generate_sample 4 --seed 1", "gpt-4_0.7_none_naive4_"),
+		];
+		for (args, prefix) in names {
+			assert!(name(args).starts_with(prefix), "{}", name(args));
+		}
+		// settings outside the readable part still change the hash
+		assert_ne!(name("--method_generate propose --method_evaluate value --method_select greedy --generator_max_tokens 50 --seed 1"), base);
+		assert_ne!(name("--method_generate propose --method_evaluate value --method_select greedy --seed 2"), base);
+	}
+
+	#[test]
+	fn counts_up_default_names_of_repeated_runs() {
+		let task_dir = std::env::temp_dir().join(format!("run-names-{}", std::process::id()));
+		assert_eq!(unused_run_name(&task_dir, "run".to_string()), "run");
+		for name in ["run", "run-2"] {
+			std::fs::create_dir_all(task_dir.join(name)).unwrap();
+			std::fs::write(task_dir.join(name).join(log::LOG_FILE), "[]").unwrap();
+		}
+		// a directory without a log, such as one left by a run that failed to start, is reused
+		std::fs::create_dir_all(task_dir.join("run-3")).unwrap();
+		assert_eq!(unused_run_name(&task_dir, "run".to_string()), "run-3");
+		assert_eq!(unused_run_name(&task_dir, "other".to_string()), "other");
+		std::fs::remove_dir_all(&task_dir).unwrap();
+	}
+}
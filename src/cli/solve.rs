@@ -0,0 +1,42 @@
+use super::{load_task, parse_opts, positionals, run::search, wants_help, Command, HELP_FLAG, MODEL_FLAGS, SEARCH_FLAGS, TASK_FLAGS};
+use crate::tasks::TaskRegistry;
+use rand::{rngs::StdRng, SeedableRng};
+use std::io::Read;
+
+pub(super) const COMMAND: Command = Command {
+	name: "solve",
+	args: "[INPUT]",
+	about: "search a single input, given as an argument or on stdin, and print the best answer",
+	flags: &[MODEL_FLAGS, TASK_FLAGS, SEARCH_FLAGS, HELP_FLAG],
+};
+
+pub(super) async fn run(mut args: pico_args::Arguments, mut registry: TaskRegistry) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let options = parse_opts(&mut args)?;
+	let input = positionals(args, &COMMAND)?.join(" ");
+	let input = if input.is_empty() {
+		let mut input = String::new();
+		std::io::stdin().read_to_string(&mut input)?;
+		input.trim_end().to_string()
+	} else {
+		input
+	};
+	if input.trim().is_empty() {
+		anyhow::bail!("Nothing to solve, give an input as an argument or on stdin");
+	}
+
+	let (_, mut task) = load_task(&mut registry, &options.task, options.task_config.as_deref(), &options.task_options)?;
+	let mut rng = StdRng::seed_from_u64(options.seed);
+	let (ys, mut info) = search(&options, task.as_mut(), &input, None, &mut rng, false).await?;
+	info.ys = ys.clone();
+	// a sampling selector keeps candidates in draw order, so rank them by the last step's values
+	let values = info.final_values();
+	let best = ys.iter().enumerate().max_by(|(i, _), (j, _)| values[*i].unwrap_or(0.0).total_cmp(&values[*j].unwrap_or(0.0)).then(j.cmp(i)));
+	let Some((_, best)) = best.filter(|(_, y)| !y.trim().is_empty()) else {
+		anyhow::bail!("No answer found");
+	};
+	println!("{}", best.trim_end());
+	Ok(())
+}
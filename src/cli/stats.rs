@@ -0,0 +1,68 @@
+use super::{positionals, wants_help, Command, HELP_FLAG};
+use crate::{
+	log,
+	metrics::{Metrics, METRICS_FILE, METRICS_SUFFIX},
+};
+use std::path::{Path, PathBuf};
+
+pub(super) const COMMAND: Command = Command {
+	name: "stats",
+	args: "[LOG|DIR]...",
+	about: "summarise run logs: puzzles, scores with 95% bootstrap intervals, tokens and cost",
+	flags: &[HELP_FLAG],
+};
+
+/// `path` itself, or every `.json` log below it when it is a directory.
+pub(super) fn log_files(path: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+	if !path.is_dir() {
+		files.push(path.to_path_buf());
+		return Ok(());
+	}
+	let mut entries = std::fs::read_dir(path)?.map(|entry| Ok(entry?.path())).collect::<anyhow::Result<Vec<_>>>()?;
+	entries.sort();
+	for entry in entries {
+		let summary = entry.file_name().and_then(|name| name.to_str()).is_some_and(|name| name == log::MANIFEST_FILE || name == METRICS_FILE || name.ends_with(METRICS_SUFFIX));
+		if entry.is_dir() || (entry.extension().is_some_and(|ext| ext == "json") && !summary) {
+			log_files(&entry, files)?;
+		}
+	}
+	Ok(())
+}
+
+pub(super) fn run(mut args: pico_args::Arguments) -> anyhow::Result<()> {
+	if wants_help(&mut args, &COMMAND) {
+		return Ok(());
+	}
+	let mut paths = positionals(args, &COMMAND)?;
+	if paths.is_empty() {
+		paths.push("logs".to_string());
+	}
+	let mut files = vec![];
+	for path in &paths {
+		log_files(Path::new(path), &mut files)?;
+	}
+
+	println!("{:>6}  {:>21}  {:>21}  {:>21}  {:>6}  {:>9}  {:>9}  log", "n", "mean of averages", "any correct", "best-of-k", "solved", "tokens", "cost");
+	for file in files {
+		let logs = log::load(&file)?;
+		// logs older than manifests sit directly in `logs/<task>/`
+		let task = match log::Manifest::find(&file)? {
+			Some(manifest) => manifest.task,
+			None => file.parent().and_then(|dir| dir.file_name()).map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+		};
+		let metrics = Metrics::of(&task, &logs, 0);
+		let usage = metrics.usage;
+		println!(
+			"{:>6}  {:>21}  {:>21}  {:>21}  {:>6}  {:>9}  {:>9.4}  {}",
+			metrics.n,
+			metrics.mean_of_averages.to_string(),
+			metrics.any_correct_text(),
+			metrics.best_of_k.to_string(),
+			metrics.solved_early,
+			usage.completion_tokens + usage.prompt_tokens,
+			usage.cost,
+			file.display()
+		);
+	}
+	Ok(())
+}
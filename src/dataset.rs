@@ -0,0 +1,342 @@
+use rand::{rngs::StdRng, seq::index::sample, SeedableRng};
+use std::{
+	collections::BTreeSet,
+	path::{Path, PathBuf},
+};
+
+/// Where a task's puzzles come from and which fields of a record matter.
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct DatasetSpec {
+	/// A file, or a directory holding split files such as `test.jsonl`. Relative paths are looked up
+	/// in the task's data directory first and then in the working directory.
+	pub path: String,
+	/// Inferred from the file extension when missing.
+	pub format: Option<DatasetFormat>,
+	/// CSV column or JSON field holding the puzzle input.
+	#[serde(default = "default_input_field")]
+	pub input_field: String,
+	/// CSV column or JSON field holding the gold answer.
+	pub answer_field: Option<String>,
+	/// CSV column or JSON field identifying a puzzle; defaults to its 0-based row.
+	pub id_field: Option<String>,
+	/// Split to read when `path` is a directory, `test` by default.
+	pub split: Option<String>,
+}
+
+fn default_input_field() -> String {
+	"input".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetFormat {
+	Csv,
+	Jsonl,
+	Json,
+	Lines,
+}
+
+impl DatasetFormat {
+	pub fn from_args(format: &str) -> anyhow::Result<Self> {
+		Ok(match format {
+			"csv" => DatasetFormat::Csv,
+			"jsonl" => DatasetFormat::Jsonl,
+			"json" => DatasetFormat::Json,
+			"lines" | "txt" => DatasetFormat::Lines,
+			format => anyhow::bail!("Invalid dataset format: {}", format),
+		})
+	}
+
+	fn from_path(path: &Path) -> anyhow::Result<Self> {
+		match path.extension().and_then(|e| e.to_str()) {
+			Some(ext) => DatasetFormat::from_args(ext),
+			None => anyhow::bail!("Cannot infer dataset format of {}, set it explicitly", path.display()),
+		}
+	}
+}
+
+/// One puzzle. `record` is the whole JSON value (or CSV row as an object) for tasks that need more than the input.
+#[derive(Debug, Clone)]
+pub struct Item {
+	pub id: String,
+	pub input: String,
+	pub answer: Option<String>,
+	pub record: serde_json::Value,
+}
+
+/// Command line overrides of a task's default [`DatasetSpec`], and which puzzles to keep.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DatasetOptions {
+	pub format: Option<DatasetFormat>,
+	pub input_field: Option<String>,
+	pub answer_field: Option<String>,
+	pub id_field: Option<String>,
+	pub split: Option<String>,
+	/// Keep only these ids, in this order.
+	pub ids: Vec<String>,
+	/// Keep a random subset of this size, drawn with `seed`.
+	pub sample: Option<usize>,
+	pub seed: u64,
+}
+
+impl DatasetOptions {
+	/// Whether the loaded dataset is already narrowed down to the puzzles to run.
+	pub fn selects(&self) -> bool {
+		!self.ids.is_empty() || self.sample.is_some()
+	}
+
+	/// `spec` with every override that was given applied; a non-empty `path` replaces the spec's path.
+	pub fn apply(&self, mut spec: DatasetSpec, path: &str) -> DatasetSpec {
+		if !path.is_empty() {
+			spec.path = path.to_string();
+		}
+		spec.format = self.format.or(spec.format);
+		if let Some(field) = &self.input_field {
+			spec.input_field = field.clone();
+		}
+		spec.answer_field = self.answer_field.clone().or(spec.answer_field);
+		spec.id_field = self.id_field.clone().or(spec.id_field);
+		spec.split = self.split.clone().or(spec.split);
+		spec
+	}
+
+	/// Applies the id filter, then the random subset.
+	pub fn select(&self, mut items: Vec<Item>) -> anyhow::Result<Vec<Item>> {
+		if !self.ids.is_empty() {
+			let mut selected = vec![];
+			for id in &self.ids {
+				let Some(item) = items.iter().find(|item| &item.id == id) else {
+					anyhow::bail!("Id {:?} not found in dataset", id);
+				};
+				selected.push(item.clone());
+			}
+			items = selected;
+		}
+		if let Some(n) = self.sample {
+			if n > items.len() {
+				anyhow::bail!("Cannot sample {} puzzles from a dataset of {}", n, items.len());
+			}
+			let mut rng = StdRng::seed_from_u64(self.seed);
+			let keep = sample(&mut rng, items.len(), n).into_iter().collect::<BTreeSet<_>>();
+			items = items.into_iter().enumerate().filter(|(i, _)| keep.contains(i)).map(|(_, item)| item).collect();
+		}
+		Ok(items)
+	}
+
+	/// Loads `default` with the overrides applied, resolving relative paths against `data_dir`.
+	pub fn load(&self, default: DatasetSpec, path: &str, data_dir: &Path) -> anyhow::Result<Vec<Item>> {
+		let spec = self.apply(default, path);
+		self.select(load(&spec, data_dir)?)
+	}
+}
+
+/// Absolute paths are used as they are; relative ones are tried in `data_dir`, then in the working directory.
+/// A directory resolves to its `<split>.<ext>` file.
+pub fn resolve_path(spec: &DatasetSpec, data_dir: &Path) -> anyhow::Result<PathBuf> {
+	let path = Path::new(&spec.path);
+	let path = if path.is_absolute() || !data_dir.join(path).exists() { path.to_path_buf() } else { data_dir.join(path) };
+	if !path.is_dir() {
+		return Ok(path);
+	}
+
+	let split = spec.split.as_deref().unwrap_or("test");
+	let candidates = ["jsonl", "json", "csv", "txt"].map(|ext| path.join(format!("{split}.{ext}")));
+	match candidates.iter().find(|candidate| candidate.is_file()) {
+		Some(file) => Ok(file.clone()),
+		None => anyhow::bail!("No {:?} split in {}", split, path.display()),
+	}
+}
+
+fn json_field(value: &serde_json::Value, field: &str) -> Option<String> {
+	match value.get(field)? {
+		serde_json::Value::String(s) => Some(s.clone()),
+		serde_json::Value::Null => None,
+		other => Some(other.to_string()),
+	}
+}
+
+pub fn load(spec: &DatasetSpec, data_dir: &Path) -> anyhow::Result<Vec<Item>> {
+	let path = resolve_path(spec, data_dir)?;
+	let format = match spec.format {
+		Some(format) => format,
+		None => DatasetFormat::from_path(&path)?,
+	};
+	let item = |row: usize, record: serde_json::Value| -> anyhow::Result<Item> {
+		// records that are not objects, like the crossword arrays, are their own input
+		let input = if record.is_object() {
+			json_field(&record, &spec.input_field).ok_or(anyhow::anyhow!("Field {:?} missing in {}", spec.input_field, record))?
+		} else {
+			record.as_str().map(|s| s.to_string()).unwrap_or_else(|| record.to_string())
+		};
+		Ok(Item {
+			id: spec.id_field.as_deref().and_then(|field| json_field(&record, field)).unwrap_or_else(|| row.to_string()),
+			input,
+			answer: spec.answer_field.as_deref().and_then(|field| json_field(&record, field)),
+			record,
+		})
+	};
+
+	match format {
+		DatasetFormat::Lines => std::fs::read_to_string(&path)?.lines().enumerate().map(|(row, line)| item(row, line.into())).collect(),
+		DatasetFormat::Jsonl => std::fs::read_to_string(&path)?
+			.lines()
+			.filter(|line| !line.trim().is_empty())
+			.enumerate()
+			.map(|(row, line)| item(row, serde_json::from_str(line)?))
+			.collect(),
+		DatasetFormat::Json => {
+			let data: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+			let serde_json::Value::Array(records) = data else {
+				anyhow::bail!("Invalid JSON file, expected Array base structure");
+			};
+			records.into_iter().enumerate().map(|(row, record)| item(row, record)).collect()
+		}
+		DatasetFormat::Csv => {
+			let mut reader = csv::ReaderBuilder::new().has_headers(true).from_path(&path)?;
+			let headers = reader.headers()?.clone();
+			if !headers.iter().any(|h| h == spec.input_field) {
+				anyhow::bail!("Column {:?} not found in {}", spec.input_field, path.display());
+			}
+			let mut items = vec![];
+			for (row, record) in reader.records().enumerate() {
+				let record = record?;
+				let record = headers.iter().zip(record.iter()).map(|(h, v)| (h.to_string(), serde_json::Value::from(v))).collect();
+				items.push(item(row, serde_json::Value::Object(record))?);
+			}
+			Ok(items)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A fresh directory with `files` written into it.
+	fn data_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("dataset-{}-{}", name, std::process::id()));
+		let _ = std::fs::remove_dir_all(&dir);
+		for (file, content) in files {
+			std::fs::create_dir_all(dir.join(file).parent().unwrap()).unwrap();
+			std::fs::write(dir.join(file), content).unwrap();
+		}
+		dir
+	}
+
+	fn spec(path: &str, input_field: &str) -> DatasetSpec {
+		DatasetSpec {
+			path: path.to_string(),
+			input_field: input_field.to_string(),
+			..Default::default()
+		}
+	}
+
+	fn inputs(items: &[Item]) -> Vec<&str> {
+		items.iter().map(|item| item.input.as_str()).collect()
+	}
+
+	#[test]
+	fn loads_csv_with_answers() {
+		let dir = data_dir("csv", &[("puzzles.csv", "rank,numbers,target\n7,3 4 5 10,62\n8,1 2 3 4,24\n")]);
+		let puzzles = DatasetSpec {
+			answer_field: Some("target".to_string()),
+			id_field: Some("rank".to_string()),
+			..spec("puzzles.csv", "numbers")
+		};
+		let items = load(&puzzles, &dir).unwrap();
+		assert_eq!(inputs(&items), ["3 4 5 10", "1 2 3 4"]);
+		assert_eq!(items[0].id, "7");
+		assert_eq!(items[1].answer.as_deref(), Some("24"));
+		assert_eq!(items[1].record["rank"], "8");
+
+		let err = load(&spec("puzzles.csv", "question"), &dir).err().unwrap();
+		assert!(err.to_string().starts_with("Column \"question\" not found"), "{err}");
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn loads_json_records_by_field() {
+		let jsonl = "{\"q\":\"What is 2 + 2?\",\"a\":4,\"id\":\"x1\"}\n\n{\"q\":\"What is 3 * 3?\",\"a\":\"9\",\"id\":\"x2\"}\n";
+		let json = r#"[{"q":"What is 1 + 1?","a":2},{"q":"What is 5 - 1?","a":null}]"#;
+		let dir = data_dir("json", &[("qa.jsonl", jsonl), ("qa.json", json)]);
+		let options = DatasetOptions {
+			input_field: Some("q".to_string()),
+			answer_field: Some("a".to_string()),
+			id_field: Some("id".to_string()),
+			..Default::default()
+		};
+
+		let items = options.load(spec("qa.jsonl", "input"), "", &dir).unwrap();
+		assert_eq!(inputs(&items), ["What is 2 + 2?", "What is 3 * 3?"]);
+		assert_eq!(items.iter().map(|item| item.answer.as_deref()).collect::<Vec<_>>(), [Some("4"), Some("9")]);
+		assert_eq!(items[1].id, "x2");
+
+		// the path argument replaces the spec's, and records without an id are numbered by row
+		let items = options.load(spec("qa.jsonl", "input"), "qa.json", &dir).unwrap();
+		assert_eq!(inputs(&items), ["What is 1 + 1?", "What is 5 - 1?"]);
+		assert_eq!(items.iter().map(|item| item.answer.as_deref()).collect::<Vec<_>>(), [Some("2"), None]);
+		assert_eq!(items[1].id, "1");
+
+		let err = load(&spec("qa.json", "question"), &dir).err().unwrap();
+		assert!(err.to_string().starts_with("Field \"question\" missing"), "{err}");
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn loads_one_item_per_line() {
+		let dir = data_dir("lines", &[("sentences", "It was dark.\nThe end.\n")]);
+		let lines = DatasetSpec {
+			format: Some(DatasetFormat::Lines),
+			..spec("sentences", "input")
+		};
+		let items = load(&lines, &dir).unwrap();
+		assert_eq!(inputs(&items), ["It was dark.", "The end."]);
+		assert_eq!(items[1].id, "1");
+		assert!(items[0].answer.is_none());
+
+		// without a format, the extension decides
+		assert!(load(&spec("sentences", "input"), &dir).err().unwrap().to_string().starts_with("Cannot infer dataset format"));
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn resolves_splits_and_paths() {
+		let dir = data_dir("splits", &[("bench/test.jsonl", "{\"input\":\"test\"}\n"), ("bench/dev.txt", "dev\n")]);
+		let bench = spec("bench", "input");
+		assert_eq!(resolve_path(&bench, &dir).unwrap(), dir.join("bench/test.jsonl"));
+		let dev = DatasetSpec { split: Some("dev".to_string()), ..bench.clone() };
+		assert_eq!(resolve_path(&dev, &dir).unwrap(), dir.join("bench/dev.txt"));
+		assert_eq!(inputs(&load(&dev, &dir).unwrap()), ["dev"]);
+		let train = DatasetSpec { split: Some("train".to_string()), ..bench.clone() };
+		assert!(resolve_path(&train, &dir).err().unwrap().to_string().starts_with("No \"train\" split in"));
+
+		// absolute paths ignore the data directory, relative ones fall back to the working directory
+		let absolute = spec(dir.join("bench").to_str().unwrap(), "input");
+		assert_eq!(resolve_path(&absolute, Path::new("elsewhere")).unwrap(), dir.join("bench/test.jsonl"));
+		assert_eq!(resolve_path(&spec("Cargo.toml", "input"), &dir).unwrap(), PathBuf::from("Cargo.toml"));
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+
+	#[test]
+	fn selects_ids_and_samples() {
+		let items = (0..20).map(|i| Item { id: format!("p{i}"), input: i.to_string(), answer: None, record: serde_json::Value::Null }).collect::<Vec<_>>();
+
+		let ids = DatasetOptions { ids: vec!["p7".to_string(), "p2".to_string()], ..Default::default() };
+		assert_eq!(inputs(&ids.select(items.clone()).unwrap()), ["7", "2"]);
+		let missing = DatasetOptions { ids: vec!["p2".to_string(), "p99".to_string()], ..Default::default() };
+		assert_eq!(missing.select(items.clone()).err().unwrap().to_string(), "Id \"p99\" not found in dataset");
+
+		let sampled = DatasetOptions { sample: Some(5), seed: 3, ..Default::default() };
+		let first = sampled.select(items.clone()).unwrap();
+		assert_eq!(first.len(), 5);
+		assert_eq!(inputs(&first), inputs(&sampled.select(items.clone()).unwrap()));
+		// the subset keeps dataset order
+		assert!(first.windows(2).all(|pair| pair[0].input.parse::<usize>().unwrap() < pair[1].input.parse::<usize>().unwrap()));
+		let reseeded = DatasetOptions { seed: 4, ..sampled.clone() };
+		assert_ne!(inputs(&first), inputs(&reseeded.select(items.clone()).unwrap()));
+
+		let too_many = DatasetOptions { sample: Some(21), ..Default::default() };
+		assert_eq!(too_many.select(items).err().unwrap().to_string(), "Cannot sample 21 puzzles from a dataset of 20");
+	}
+}
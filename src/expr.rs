@@ -0,0 +1,199 @@
+/// Evaluates an arithmetic expression over `+ - * /`, parentheses and unary minus.
+/// Returns `None` for malformed input or division by zero.
+pub fn eval(expression: &str) -> Option<f64> {
+	Parser::new(expression)?.parse()
+}
+
+/// The numbers `expression` combines, in order, and the operations it applies to them. A minus sign right
+/// before a number is part of that number, so `-3 * 8` combines -3 and 8 with `*`; before a parenthesis it is a `-`.
+/// Returns `None` where [`eval`] does.
+pub fn operands(expression: &str) -> Option<(Vec<f64>, Vec<char>)> {
+	let mut parser = Parser::new(expression)?;
+	parser.parse()?;
+	Some((parser.numbers, parser.operators))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+	Num(f64),
+	Op(char),
+	Open,
+	Close,
+}
+
+fn tokenize(expression: &str) -> Option<Vec<Token>> {
+	let mut tokens = vec![];
+	let mut chars = expression.chars().peekable();
+	while let Some(&c) = chars.peek() {
+		match c {
+			c if c.is_whitespace() => {
+				chars.next();
+			}
+			'0'..='9' | '.' => {
+				let mut number = String::new();
+				while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit() || **d == '.') {
+					number.push(d);
+					chars.next();
+				}
+				tokens.push(Token::Num(number.parse().ok()?));
+			}
+			'+' | '-' | '*' | '/' => {
+				tokens.push(Token::Op(c));
+				chars.next();
+			}
+			'(' => {
+				tokens.push(Token::Open);
+				chars.next();
+			}
+			')' => {
+				tokens.push(Token::Close);
+				chars.next();
+			}
+			_ => return None,
+		}
+	}
+	Some(tokens)
+}
+
+struct Parser {
+	tokens: Vec<Token>,
+	pos: usize,
+	/// Numbers and operations read so far.
+	numbers: Vec<f64>,
+	operators: Vec<char>,
+}
+
+impl Parser {
+	fn new(expression: &str) -> Option<Parser> {
+		Some(Parser {
+			tokens: tokenize(expression)?,
+			pos: 0,
+			numbers: vec![],
+			operators: vec![],
+		})
+	}
+
+	fn parse(&mut self) -> Option<f64> {
+		let value = self.expr()?;
+		(self.pos == self.tokens.len()).then_some(value)
+	}
+
+	fn peek(&self) -> Option<Token> {
+		self.tokens.get(self.pos).copied()
+	}
+
+	fn expr(&mut self) -> Option<f64> {
+		let mut value = self.term()?;
+		while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+			self.pos += 1;
+			self.operators.push(op);
+			let rhs = self.term()?;
+			value = if op == '+' { value + rhs } else { value - rhs };
+		}
+		Some(value)
+	}
+
+	fn term(&mut self) -> Option<f64> {
+		let mut value = self.factor()?;
+		while let Some(Token::Op(op @ ('*' | '/'))) = self.peek() {
+			self.pos += 1;
+			self.operators.push(op);
+			let rhs = self.factor()?;
+			value = if op == '*' {
+				value * rhs
+			} else if rhs == 0.0 {
+				return None;
+			} else {
+				value / rhs
+			};
+		}
+		Some(value)
+	}
+
+	fn factor(&mut self) -> Option<f64> {
+		let token = self.peek()?;
+		self.pos += 1;
+		match token {
+			Token::Num(n) => {
+				self.numbers.push(n);
+				Some(n)
+			}
+			Token::Op('-') => match self.peek()? {
+				Token::Num(n) => {
+					self.pos += 1;
+					self.numbers.push(-n);
+					Some(-n)
+				}
+				_ => {
+					self.operators.push('-');
+					Some(-self.factor()?)
+				}
+			},
+			Token::Open => {
+				let value = self.expr()?;
+				if self.peek()? != Token::Close {
+					return None;
+				}
+				self.pos += 1;
+				Some(value)
+			}
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{eval, operands};
+
+	#[test]
+	fn precedence() {
+		assert_eq!(eval("2 + 3 * 4"), Some(14.0));
+		assert_eq!(eval("2 * 3 + 4"), Some(10.0));
+		assert_eq!(eval("10 - 6 / 2"), Some(7.0));
+	}
+
+	#[test]
+	fn left_associativity() {
+		assert_eq!(eval("8 - 4 - 2"), Some(2.0));
+		assert_eq!(eval("8 / 4 / 2"), Some(1.0));
+		assert_eq!(eval("8 - 4 + 2"), Some(6.0));
+	}
+
+	#[test]
+	fn unary_minus_and_parentheses() {
+		assert_eq!(eval("-3 + 5"), Some(2.0));
+		assert_eq!(eval("4 * -2"), Some(-8.0));
+		assert_eq!(eval("-(1 + 2) * 3"), Some(-9.0));
+		assert_eq!(eval("(8 - 4) - 2"), Some(2.0));
+		assert_eq!(eval("8 - (4 - 2)"), Some(6.0));
+		assert_eq!(eval("(1 + 2) * (3 + 4)"), Some(21.0));
+		assert_eq!(eval("2.5 * 4"), Some(10.0));
+	}
+
+	#[test]
+	fn division_by_zero() {
+		assert_eq!(eval("1 / 0"), None);
+		assert_eq!(eval("4 / (2 - 2)"), None);
+	}
+
+	#[test]
+	fn malformed_input() {
+		assert_eq!(eval("1 + 2)"), None);
+		assert_eq!(eval("(1 + 2"), None);
+		assert_eq!(eval("1 2"), None);
+		assert_eq!(eval("1 +"), None);
+		assert_eq!(eval("3 * 8 = 24"), None);
+		assert_eq!(eval(""), None);
+	}
+
+	#[test]
+	fn operands_keep_their_signs() {
+		assert_eq!(operands("4 * (9 - 3) / 1"), Some((vec![4.0, 9.0, 3.0, 1.0], vec!['*', '-', '/'])));
+		assert_eq!(operands("-3 * 8"), Some((vec![-3.0, 8.0], vec!['*'])));
+		assert_eq!(operands("5 - -3"), Some((vec![5.0, -3.0], vec!['-'])));
+		assert_eq!(operands("-(2 + 3)"), Some((vec![2.0, 3.0], vec!['-', '+'])));
+		assert_eq!(operands("1 / 0"), None);
+		assert_eq!(operands("1 + 2)"), None);
+	}
+}
@@ -0,0 +1,14 @@
+pub mod cache;
+pub mod cli;
+pub mod dataset;
+pub mod expr;
+pub mod log;
+pub mod metrics;
+pub mod models;
+pub mod prompts;
+pub mod select;
+pub mod strings;
+pub mod tasks;
+pub mod template;
+pub mod tokens;
+pub mod votes;
@@ -0,0 +1,163 @@
+use crate::{tasks::TOutput, votes::VoteOutcome};
+use std::path::{Path, PathBuf};
+
+/// Where runs are logged unless `--log_dir` is given, one `<task>/<run name>` directory each.
+pub const DEFAULT_LOG_DIR: &str = "./logs";
+/// Name of the log in a run directory.
+pub const LOG_FILE: &str = "log.json";
+/// Name of the manifest next to it.
+pub const MANIFEST_FILE: &str = "run.json";
+
+/// One BFS step: the frontier `ys`, the candidates it expanded into and which of them were kept.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct InfoData {
+	pub step: i32,
+	pub x: String,
+	pub ys: Vec<String>,
+	pub new_ys: Vec<String>,
+	pub values: Vec<f32>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub votes: Vec<VoteOutcome>,
+	pub select_new_ys: Vec<String>,
+}
+
+/// Everything logged for one puzzle.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct AllInfo {
+	pub steps: Vec<InfoData>,
+	/// Position of the puzzle in the loaded dataset, after any `--ids` or `--sample` filter.
+	pub idx: isize,
+	/// Dataset id of the puzzle.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub id: Option<String>,
+	#[serde(default)]
+	pub seed: u64,
+	/// search depth at which a candidate first passed the task verifier
+	#[serde(default)]
+	pub solved_depth: Option<isize>,
+	pub ys: Vec<String>,
+	pub infos: Vec<TOutput>,
+	pub usage_so_far: (u32, u32, f64),
+	/// Why the search or scoring of the puzzle failed, such as a request the API rejected.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+impl AllInfo {
+	pub fn new() -> Self {
+		AllInfo::default()
+	}
+
+	/// The puzzle input, as recorded by the first step.
+	pub fn x(&self) -> &str {
+		self.steps.first().map_or("", |step| step.x.as_str())
+	}
+
+	/// The value the last step gave each final candidate; `None` where it valued none, as in naive runs.
+	pub fn final_values(&self) -> Vec<Option<f32>> {
+		let value = |y: &String| {
+			let step = self.steps.last()?;
+			step.new_ys.iter().position(|new_y| new_y == y).and_then(|i| step.values.get(i)).copied()
+		};
+		self.ys.iter().map(value).collect()
+	}
+}
+
+/// Entry of logs written before steps were kept: only the last step, flattened into the puzzle record.
+#[derive(Debug, serde::Deserialize)]
+struct LegacyInfo {
+	step: i32,
+	x: String,
+	ys_i: Vec<String>,
+	new_ys: Vec<String>,
+	values: Vec<f32>,
+	select_new_ys: Vec<String>,
+	idx: isize,
+	#[serde(rename = "_infos_i")]
+	infos: Vec<TOutput>,
+	usage_so_far: (u32, u32, f64),
+}
+
+impl From<LegacyInfo> for AllInfo {
+	fn from(legacy: LegacyInfo) -> Self {
+		AllInfo {
+			steps: vec![InfoData {
+				step: legacy.step,
+				x: legacy.x,
+				ys: legacy.ys_i,
+				new_ys: legacy.new_ys,
+				values: legacy.values,
+				votes: vec![],
+				select_new_ys: legacy.select_new_ys.clone(),
+			}],
+			idx: legacy.idx,
+			id: None,
+			seed: 0,
+			solved_depth: None,
+			ys: legacy.select_new_ys,
+			infos: legacy.infos,
+			usage_so_far: legacy.usage_so_far,
+			error: None,
+		}
+	}
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum Entry {
+	Current(AllInfo),
+	Legacy(LegacyInfo),
+}
+
+/// Reads a run log, accepting the older flattened layout as well.
+pub fn load(path: &Path) -> anyhow::Result<Vec<AllInfo>> {
+	let content = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read log {}: {}", path.display(), e))?;
+	let entries: Vec<Entry> = serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid log {}: {}", path.display(), e))?;
+	Ok(entries
+		.into_iter()
+		.map(|entry| match entry {
+			Entry::Current(info) => info,
+			Entry::Legacy(legacy) => legacy.into(),
+		})
+		.collect())
+}
+
+pub fn save(path: &Path, logs: &[AllInfo]) -> anyhow::Result<()> {
+	let file = std::fs::File::create(path).map_err(|e| anyhow::anyhow!("Cannot create log {}: {}", path.display(), e))?;
+	serde_json::to_writer(file, logs)?;
+	Ok(())
+}
+
+/// How a run was started, written as `run.json` next to its log.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Manifest {
+	pub run_name: String,
+	pub task: String,
+	/// Seconds since the Unix epoch.
+	pub started: u64,
+	/// The flags of the run, including those from an experiment config.
+	pub args: Vec<String>,
+	/// Parameters of an experiment grid that picked this run.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub swept: Vec<(String, String)>,
+	/// Every setting of the run, as parsed.
+	pub options: serde_json::Value,
+	pub puzzles: Vec<usize>,
+}
+
+impl Manifest {
+	pub fn save(&self, dir: &Path) -> anyhow::Result<()> {
+		let path = dir.join(MANIFEST_FILE);
+		std::fs::write(&path, serde_json::to_string_pretty(self)?).map_err(|e| anyhow::anyhow!("Cannot write manifest {}: {}", path.display(), e))
+	}
+
+	/// The manifest next to `log`; older logs have none.
+	pub fn find(log: &Path) -> anyhow::Result<Option<Manifest>> {
+		let path = log.parent().map_or_else(|| PathBuf::from(MANIFEST_FILE), |dir| dir.join(MANIFEST_FILE));
+		if !path.is_file() {
+			return Ok(None);
+		}
+		let content = std::fs::read_to_string(&path)?;
+		Ok(Some(serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))?))
+	}
+}
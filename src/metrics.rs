@@ -0,0 +1,295 @@
+use crate::{log::AllInfo, tasks::TOutput};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::path::{Path, PathBuf};
+
+/// Name of the metrics file in a run directory, next to the log.
+pub const METRICS_FILE: &str = "metrics.json";
+/// Suffix of the metrics file of a log outside a run directory, `<stem>.metrics.json`.
+pub const METRICS_SUFFIX: &str = ".metrics.json";
+/// Resamples drawn for a bootstrap interval.
+pub const BOOTSTRAP_RESAMPLES: usize = 1000;
+/// Coverage of the bootstrap intervals.
+pub const CONFIDENCE: f64 = 0.95;
+
+/// How one puzzle scored, from the verifier results of its final candidates.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PuzzleScore {
+	pub idx: isize,
+	/// Final candidates that were scored.
+	pub k: usize,
+	/// Mean `r` over the candidates, 0 when there are none.
+	pub mean: f64,
+	/// Whether any candidate is correct (pass@k); `None` when no candidate got a verdict.
+	pub any_correct: Option<bool>,
+	/// `r` of the candidate the search valued highest, or of the first one when it valued none.
+	pub best_of_k: f64,
+}
+
+/// Whether `output` passes the verifier of `task`: the verdict the task recorded, or for logs written
+/// before tasks recorded one, the built-in tasks' own checks. Scores such as Text's 1-10 coherence are not verdicts.
+pub fn verdict(task: &str, output: &TOutput) -> Option<bool> {
+	output.correct.or(match task {
+		// `r` is 0 or 1 for these, and older logs did not set `r_game`
+		"game24" | "countdown" => Some(output.r_game || output.r >= 1.0),
+		"crosswords" => Some(output.r_word == 1.0),
+		"text" => Some(output.r_constraint == 1.0),
+		_ => None,
+	})
+}
+
+impl PuzzleScore {
+	/// Scores the final candidates of a puzzle of `task`; a candidate is correct by its [`verdict`].
+	pub fn of(task: &str, info: &AllInfo) -> PuzzleScore {
+		let rs = info.infos.iter().map(|output| output.r as f64).collect::<Vec<_>>();
+		let values = info.final_values();
+		let value = |i: usize| values.get(i).copied().flatten().unwrap_or(f32::NEG_INFINITY);
+		// ties go to the earlier candidate
+		let best = (0..rs.len()).max_by(|a, b| value(*a).total_cmp(&value(*b)).then(b.cmp(a)));
+		PuzzleScore {
+			idx: info.idx,
+			k: rs.len(),
+			mean: if rs.is_empty() { 0.0 } else { rs.iter().sum::<f64>() / rs.len() as f64 },
+			any_correct: info.infos.iter().filter_map(|output| verdict(task, output)).reduce(|a, b| a || b),
+			best_of_k: best.map_or(0.0, |best| rs[best]),
+		}
+	}
+}
+
+/// A mean over puzzles with its percentile bootstrap interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Estimate {
+	pub value: f64,
+	pub ci_low: f64,
+	pub ci_high: f64,
+}
+
+impl Estimate {
+	/// The mean of `samples` and its [`CONFIDENCE`] interval from [`BOOTSTRAP_RESAMPLES`] resamples.
+	pub fn bootstrap<R: Rng>(samples: &[f64], rng: &mut R) -> Estimate {
+		let n = samples.len();
+		if n == 0 {
+			return Estimate::default();
+		}
+		let mut means = (0..BOOTSTRAP_RESAMPLES).map(|_| (0..n).map(|_| samples[rng.gen_range(0..n)]).sum::<f64>() / n as f64).collect::<Vec<_>>();
+		means.sort_by(f64::total_cmp);
+		let quantile = |q: f64| means[((q * BOOTSTRAP_RESAMPLES as f64) as usize).min(BOOTSTRAP_RESAMPLES - 1)];
+		let tail = (1.0 - CONFIDENCE) / 2.0;
+		Estimate {
+			value: samples.iter().sum::<f64>() / n as f64,
+			ci_low: quantile(tail),
+			ci_high: quantile(1.0 - tail),
+		}
+	}
+}
+
+impl std::fmt::Display for Estimate {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{:.3} [{:.3}, {:.3}]", self.value, self.ci_low, self.ci_high)
+	}
+}
+
+/// Tokens and dollars a run spent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Usage {
+	pub completion_tokens: u32,
+	pub prompt_tokens: u32,
+	pub cost: f64,
+}
+
+/// Summary of one run of a task, written as `metrics.json` next to its log.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Metrics {
+	pub task: String,
+	/// Puzzles in the log.
+	pub n: usize,
+	/// Puzzles whose search stopped early on a verified candidate.
+	pub solved_early: usize,
+	/// Puzzles whose search or scoring failed; they count as unsolved.
+	#[serde(default)]
+	pub failed: usize,
+	/// Mean over puzzles of the mean `r` of their final candidates.
+	pub mean_of_averages: Estimate,
+	/// Share of puzzles with at least one correct final candidate (pass@k); `None` for tasks without a verifier.
+	pub any_correct: Option<Estimate>,
+	/// Mean `r` of the candidate the search valued highest.
+	pub best_of_k: Estimate,
+	pub confidence: f64,
+	pub usage: Usage,
+	pub puzzles: Vec<PuzzleScore>,
+}
+
+impl Metrics {
+	/// Metrics of the puzzles in `logs`; `seed` fixes the bootstrap resamples so reruns report the same intervals.
+	pub fn of(task: &str, logs: &[AllInfo], seed: u64) -> Metrics {
+		let puzzles = logs.iter().map(|info| PuzzleScore::of(task, info)).collect::<Vec<_>>();
+		let mut rng = StdRng::seed_from_u64(seed);
+		let mut estimate = |score: fn(&PuzzleScore) -> f64| Estimate::bootstrap(&puzzles.iter().map(score).collect::<Vec<_>>(), &mut rng);
+		let mean_of_averages = estimate(|p| p.mean);
+		let best_of_k = estimate(|p| p.best_of_k);
+		// puzzles that failed or have no candidates still count as unsolved for a task with a verifier
+		let verified = puzzles.iter().any(|p| p.any_correct.is_some());
+		let any_correct = verified.then(|| estimate(|p| if p.any_correct == Some(true) { 1.0 } else { 0.0 }));
+		// usage is cumulative, so the last entry holds the run's total
+		let (completion_tokens, prompt_tokens, cost) = logs.last().map_or((0, 0, 0.0), |info| info.usage_so_far);
+		Metrics {
+			task: task.to_string(),
+			n: logs.len(),
+			solved_early: logs.iter().filter(|info| info.solved_depth.is_some()).count(),
+			failed: logs.iter().filter(|info| info.error.is_some()).count(),
+			mean_of_averages,
+			any_correct,
+			best_of_k,
+			confidence: CONFIDENCE,
+			usage: Usage {
+				completion_tokens,
+				prompt_tokens,
+				cost,
+			},
+			puzzles,
+		}
+	}
+
+	/// The pass@k estimate, or `n/a` for a task without a verifier.
+	pub fn any_correct_text(&self) -> String {
+		self.any_correct.map_or_else(|| "n/a".to_string(), |estimate| estimate.to_string())
+	}
+
+	/// Writes the metrics of `log` to [`path_for`] it.
+	pub fn save(&self, log: &Path) -> anyhow::Result<()> {
+		let path = path_for(log);
+		std::fs::write(&path, serde_json::to_string_pretty(self)?).map_err(|e| anyhow::anyhow!("Cannot write metrics {}: {}", path.display(), e))
+	}
+}
+
+/// Where the metrics of `log` go: [`METRICS_FILE`] in a run directory, or `<stem>.metrics.json` next to an older log.
+pub fn path_for(log: &Path) -> PathBuf {
+	if log.file_name().is_some_and(|name| name == crate::log::LOG_FILE) {
+		return log.with_file_name(METRICS_FILE);
+	}
+	let stem = log.file_stem().map_or_else(Default::default, |stem| stem.to_string_lossy().into_owned());
+	log.with_file_name(format!("{stem}{METRICS_SUFFIX}"))
+}
+
+impl std::fmt::Display for Metrics {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		writeln!(
+			f,
+			"task: {}, puzzles: {}, solved early: {}, failed: {} ({:.0}% bootstrap intervals)",
+			self.task,
+			self.n,
+			self.solved_early,
+			self.failed,
+			self.confidence * 100.0
+		)?;
+		writeln!(f, "mean of averages: {}", self.mean_of_averages)?;
+		writeln!(f, "any correct (pass@k): {}", self.any_correct_text())?;
+		writeln!(f, "best-of-k by value: {}", self.best_of_k)?;
+		write!(f, "usage: {} completion + {} prompt tokens, ${:.4}", self.usage.completion_tokens, self.usage.prompt_tokens, self.usage.cost)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::log::InfoData;
+
+	fn output(r: f32, correct: Option<bool>) -> TOutput {
+		TOutput { r, correct, ..TOutput::new() }
+	}
+
+	/// A puzzle whose final candidates scored `outputs`, valued `values` by the last step.
+	fn puzzle(idx: isize, outputs: Vec<TOutput>, values: &[f32]) -> AllInfo {
+		let ys = (0..outputs.len()).map(|i| format!("y{i}")).collect::<Vec<_>>();
+		AllInfo {
+			idx,
+			steps: vec![InfoData {
+				new_ys: ys.clone(),
+				values: values.to_vec(),
+				..Default::default()
+			}],
+			ys,
+			infos: outputs,
+			..AllInfo::new()
+		}
+	}
+
+	#[test]
+	fn bootstrap_of_constant_samples_has_no_width() {
+		let mut rng = StdRng::seed_from_u64(0);
+		assert_eq!(Estimate::bootstrap(&[], &mut rng), Estimate::default());
+		let estimate = Estimate::bootstrap(&[0.5; 4], &mut rng);
+		assert_eq!((estimate.value, estimate.ci_low, estimate.ci_high), (0.5, 0.5, 0.5));
+	}
+
+	#[test]
+	fn bootstrap_interval_of_two_outcomes() {
+		// resampling [0, 1] gives means 0, 1/2 and 1 with odds 1:2:1, so the 2.5% and 97.5% quantiles
+		// of 1000 resamples (the 26th and 976th smallest) are 0 and 1 but for a ~1e-25 chance
+		let estimate = Estimate::bootstrap(&[0.0, 1.0], &mut StdRng::seed_from_u64(1));
+		assert_eq!((estimate.value, estimate.ci_low, estimate.ci_high), (0.5, 0.0, 1.0));
+		// with one success in four, every resample mean is a multiple of 1/4 around 0.25
+		let estimate = Estimate::bootstrap(&[0.0, 0.0, 0.0, 1.0], &mut StdRng::seed_from_u64(2));
+		assert_eq!((estimate.value, estimate.ci_low), (0.25, 0.0));
+		assert!([0.5, 0.75].contains(&estimate.ci_high), "{estimate}");
+	}
+
+	#[test]
+	fn puzzle_scores() {
+		let score = PuzzleScore::of("game24", &puzzle(3, vec![output(0.0, Some(false)), output(1.0, Some(true)), output(0.0, Some(false))], &[2.0, 1.0, 2.0]));
+		assert_eq!((score.idx, score.k, score.any_correct), (3, 3, Some(true)));
+		assert!((score.mean - 1.0 / 3.0).abs() < 1e-12);
+		// the search valued the first and last candidates highest; the earlier one wins the tie
+		assert_eq!(score.best_of_k, 0.0);
+
+		let empty = PuzzleScore::of("game24", &AllInfo::new());
+		assert_eq!((empty.k, empty.mean, empty.any_correct, empty.best_of_k), (0, 0.0, None, 0.0));
+	}
+
+	#[test]
+	fn verdicts_are_per_task() {
+		// logs from before tasks recorded a verdict
+		let legacy = |r: f32| output(r, None);
+		assert_eq!(verdict("game24", &legacy(1.0)), Some(true));
+		assert_eq!(verdict("countdown", &legacy(0.0)), Some(false));
+		let words = TOutput { r_word: 0.8, r_game: true, ..legacy(0.8) };
+		assert_eq!(verdict("crosswords", &words), Some(false));
+		// a coherence score of 9 does not make a passage correct, its paragraph endings do
+		let passage = TOutput { r_constraint: 0.75, ..legacy(9.0) };
+		assert_eq!(verdict("text", &passage), Some(false));
+		assert_eq!(verdict("text", &TOutput { r_constraint: 1.0, ..passage }), Some(true));
+		assert_eq!(verdict("my_benchmark", &legacy(10.0)), None);
+		// a recorded verdict wins
+		assert_eq!(verdict("my_benchmark", &output(0.0, Some(true))), Some(true));
+		assert_eq!(verdict("text", &output(0.0, Some(true))), Some(true));
+	}
+
+	#[test]
+	fn pass_at_k_counts_failed_puzzles_as_unsolved() {
+		let logs = vec![
+			puzzle(0, vec![output(0.0, Some(false)), output(1.0, Some(true))], &[1.0, 2.0]),
+			puzzle(1, vec![output(0.0, Some(false))], &[1.0]),
+			puzzle(2, vec![output(1.0, Some(true))], &[1.0]),
+			AllInfo {
+				idx: 3,
+				error: Some("Request to gpt-4 failed".to_string()),
+				..AllInfo::new()
+			},
+		];
+		let metrics = Metrics::of("game24", &logs, 0);
+		assert_eq!((metrics.n, metrics.failed), (4, 1));
+		assert_eq!(metrics.any_correct.unwrap().value, 0.5);
+		assert_eq!(metrics.best_of_k.value, 0.5);
+		assert_eq!(metrics.mean_of_averages.value, (0.5 + 0.0 + 1.0 + 0.0) / 4.0);
+		assert_eq!(metrics.puzzles.iter().map(|p| p.any_correct).collect::<Vec<_>>(), [Some(true), Some(false), Some(true), None]);
+	}
+
+	#[test]
+	fn pass_at_k_is_na_without_a_verifier() {
+		let logs = vec![puzzle(0, vec![output(7.0, None), output(9.0, None)], &[1.0, 1.0])];
+		let metrics = Metrics::of("my_benchmark", &logs, 0);
+		assert_eq!(metrics.any_correct, None);
+		assert_eq!(metrics.any_correct_text(), "n/a");
+		assert!(metrics.to_string().contains("any correct (pass@k): n/a"), "{metrics}");
+		assert_eq!(metrics.mean_of_averages.value, 8.0);
+	}
+}
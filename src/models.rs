@@ -1,3 +1,9 @@
+use crate::{
+	cache,
+	strings::SUMMARISE_PROMPT,
+	template::{Template, Vars},
+	tokens,
+};
 use async_openai::{
 	error::OpenAIError,
 	types::{ChatCompletionRequestMessage, ChatCompletionRequestMessageArgs, CreateChatCompletionRequestArgs, CreateChatCompletionResponse, Role},
@@ -5,9 +11,256 @@ use async_openai::{
 };
 use backoff::future::retry;
 use backoff::ExponentialBackoff;
+use std::{collections::BTreeMap, fmt, sync::Mutex};
+
+pub const MODELS: [&str; 2] = ["gpt-4", "gpt-3.5-turbo"];
+
+/// (completion, prompt) tokens used so far, per model
+static USAGE: Mutex<BTreeMap<String, (u32, u32)>> = Mutex::new(BTreeMap::new());
+
+/// A prompt as sent to the model: a flat string, sent as one user message, or chat turns.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prompt {
+	Text(String),
+	Chat(Vec<ChatCompletionRequestMessage>),
+}
+
+impl Prompt {
+	/// An empty chat prompt, to be built up with [`Prompt::turn`].
+	pub fn chat() -> Prompt {
+		Prompt::Chat(vec![])
+	}
+
+	/// Appends a turn, merging it into the last one when the role repeats. A flat prompt becomes its first user turn.
+	pub fn turn(self, role: Role, content: &str) -> Prompt {
+		let mut messages = self.messages();
+		match messages.last_mut() {
+			Some(last) if last.role == role => {
+				last.content.push('\n');
+				last.content.push_str(content);
+			}
+			_ => messages.push(message(role, content)),
+		}
+		Prompt::Chat(messages)
+	}
+
+	/// Appends text to the end of the prompt, i.e. to its last turn.
+	pub fn push_str(&mut self, text: &str) {
+		match self {
+			Prompt::Text(prompt) => prompt.push_str(text),
+			Prompt::Chat(messages) => match messages.last_mut() {
+				Some(last) => last.content.push_str(text),
+				None => messages.push(message(Role::User, text)),
+			},
+		}
+	}
+
+	/// Tokens the prompt takes in a request to `model`, including the per-message chat framing.
+	pub fn tokens(&self, model: &str) -> usize {
+		let tokenizer = tokens::tokenizer(model);
+		let messages = match self {
+			Prompt::Text(prompt) => return 4 + tokenizer.count(prompt) + 3,
+			Prompt::Chat(messages) => messages,
+		};
+		// every message costs `<|start|>role\n ... <|end|>`, and the reply is primed with 3 more
+		messages.iter().map(|m| 4 + tokenizer.count(&m.content)).sum::<usize>() + 3
+	}
+
+	/// Drops few-shot turns (everything between the system turns and the last user turn) from the oldest on,
+	/// keeping whole user/assistant pairs, until `fits` accepts the prompt.
+	fn truncate_examples(&self, fits: impl Fn(&Prompt) -> bool) -> Option<Prompt> {
+		let Prompt::Chat(messages) = self else {
+			return None;
+		};
+		let first_example = messages.iter().position(|m| m.role != Role::System)?;
+		let query = messages.iter().rposition(|m| m.role == Role::User)?;
+		let mut messages = messages.clone();
+		let mut examples = query - first_example;
+		while examples > 0 {
+			let pair = if examples >= 2 && messages[first_example + 1].role == Role::Assistant { 2 } else { 1 };
+			messages.drain(first_example..first_example + pair);
+			examples -= pair;
+			let prompt = Prompt::Chat(messages.clone());
+			if fits(&prompt) {
+				return Some(prompt);
+			}
+		}
+		None
+	}
+
+	pub fn messages(self) -> Vec<ChatCompletionRequestMessage> {
+		match self {
+			Prompt::Text(prompt) => vec![message(Role::User, &prompt)],
+			Prompt::Chat(messages) => messages,
+		}
+	}
+}
+
+impl From<String> for Prompt {
+	fn from(prompt: String) -> Self {
+		Prompt::Text(prompt)
+	}
+}
+
+impl From<&str> for Prompt {
+	fn from(prompt: &str) -> Self {
+		Prompt::Text(prompt.to_string())
+	}
+}
+
+/// The flat text, or every turn prefixed with its role; used for logs and cache keys.
+impl fmt::Display for Prompt {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Prompt::Text(prompt) => f.write_str(prompt),
+			Prompt::Chat(messages) => {
+				for m in messages {
+					let role = match m.role {
+						Role::System => "system",
+						Role::User => "user",
+						Role::Assistant => "assistant",
+					};
+					writeln!(f, "[{}]\n{}", role, m.content)?;
+				}
+				Ok(())
+			}
+		}
+	}
+}
+
+fn message(role: Role, content: &str) -> ChatCompletionRequestMessage {
+	ChatCompletionRequestMessageArgs::default().role(role).content(content).build().unwrap()
+}
+
+/// What to do with a prompt that does not leave `max_tokens` free in the model's context window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextPolicy {
+	/// Drop few-shot example turns of a chat prompt, oldest first. Needs `--chat_prompts`.
+	TruncateExamples,
+	/// Have the model summarise the longest candidates of a vote prompt.
+	Summarise,
+	/// Leave the longest candidates out of a vote prompt; they get no votes.
+	DropCandidates,
+	/// Send nothing and fail the puzzle.
+	#[default]
+	Fail,
+}
+
+impl ContextPolicy {
+	pub fn from_args(policy: &str) -> anyhow::Result<Self> {
+		Ok(match policy {
+			"truncate_examples" => ContextPolicy::TruncateExamples,
+			"summarise" | "summarize" => ContextPolicy::Summarise,
+			"drop_candidates" => ContextPolicy::DropCandidates,
+			"fail" => ContextPolicy::Fail,
+			policy => anyhow::bail!("Invalid context_policy: {}", policy),
+		})
+	}
+
+	/// Rejects policies that cannot apply to the prompts a task sends; flat prompts have no example turns to drop.
+	pub fn check(self, chat_prompts: bool) -> anyhow::Result<()> {
+		if self == ContextPolicy::TruncateExamples && !chat_prompts {
+			anyhow::bail!("--context_policy truncate_examples only drops the example turns of chat prompts, pass --chat_prompts too");
+		}
+		Ok(())
+	}
+}
+
+/// Model and sampling settings for one role in the search (generator, evaluator or scorer).
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ModelConfig {
+	pub model: String,
+	pub temperature: f32,
+	pub max_tokens: u16,
+	pub context_policy: ContextPolicy,
+}
+
+impl ModelConfig {
+	pub fn new(model: &str, temperature: f32, max_tokens: u16, context_policy: ContextPolicy) -> anyhow::Result<Self> {
+		if !MODELS.contains(&model) {
+			anyhow::bail!("Invalid model: {}", model);
+		}
+		if max_tokens as usize >= context_window(model) {
+			anyhow::bail!("max_tokens {} does not fit the {} token context of {}", max_tokens, context_window(model), model);
+		}
+		Ok(ModelConfig {
+			model: model.to_string(),
+			temperature,
+			max_tokens,
+			context_policy,
+		})
+	}
+
+	/// Whether `prompt` leaves room for `max_tokens` of completion.
+	pub fn fits(&self, prompt: &Prompt) -> bool {
+		prompt.tokens(&self.model) + self.max_tokens as usize <= context_window(&self.model)
+	}
+
+	/// Completes `prompt`, first applying the context policy if it is too long. Answers come from the completion cache when it is enabled.
+	/// Fails without sending anything when the prompt still does not fit, and when the API rejects the request for good
+	/// or keeps failing past the retries.
+	pub async fn complete(&self, prompt: &Prompt, n: isize, stop: Option<&str>) -> anyhow::Result<Vec<String>> {
+		let prompt = if self.fits(prompt) {
+			prompt.clone()
+		} else {
+			let truncated = match self.context_policy {
+				ContextPolicy::TruncateExamples => prompt.truncate_examples(|p| self.fits(p)),
+				_ => None,
+			};
+			let Some(truncated) = truncated else {
+				anyhow::bail!(
+					"Prompt of {} tokens leaves no room for {} completion tokens in the {} token context of {} (context policy {:?})",
+					prompt.tokens(&self.model),
+					self.max_tokens,
+					context_window(&self.model),
+					self.model,
+					self.context_policy
+				);
+			};
+			truncated
+		};
+		let mut request = cache::Entry::new(&self.model, self.temperature, self.max_tokens, n, stop, &prompt);
+		if let Some(outputs) = cache::get(&request) {
+			return Ok(outputs);
+		}
+		request.outputs = chatgpt(prompt.messages(), &self.model, self.temperature, self.max_tokens, n, stop).await?;
+		if !request.outputs.is_empty() {
+			cache::put(&request);
+		}
+		Ok(request.outputs)
+	}
 
-static mut COMPLETION_TOKENS: u32 = 0;
-static mut PROMPT_TOKENS: u32 = 0;
+	/// A shorter version of `text` by the model, or `text` itself if the model gives none or it is too long to summarise.
+	pub async fn summarise(&self, text: &str) -> anyhow::Result<String> {
+		let template = Template::parse(SUMMARISE_PROMPT).expect("built-in summarise prompt is valid");
+		let prompt = Prompt::Text(template.render(&Vars::new().text("passage", text)));
+		if !self.fits(&prompt) {
+			return Ok(text.to_string());
+		}
+		let summary = ModelConfig { context_policy: ContextPolicy::Fail, ..self.clone() }.complete(&prompt, 1, None).await?;
+		Ok(summary.into_iter().next().filter(|s| !s.trim().is_empty()).unwrap_or_else(|| text.to_string()))
+	}
+}
+
+/// Context window, in tokens, shared by the prompt and the completion.
+pub fn context_window(model: &str) -> usize {
+	match model {
+		"gpt-4" => 8192,
+		"gpt-3.5-turbo" => 4096,
+		_ => panic!("Invalid backend"),
+	}
+}
+
+/// Errors that will not go away by sending the same request again.
+fn is_permanent(error: &OpenAIError) -> bool {
+	match error {
+		OpenAIError::ApiError(e) => e.r#type == "invalid_request_error",
+		OpenAIError::Reqwest(e) => e.status().is_some_and(|status| status.is_client_error() && status.as_u16() != 429),
+		OpenAIError::InvalidArgument(_) | OpenAIError::JSONDeserialize(_) => true,
+		_ => false,
+	}
+}
 
 pub async fn completions_with_backoff(
 	model:&str,
@@ -27,50 +280,111 @@ pub async fn completions_with_backoff(
 		.messages(messages.to_owned());
 
 	stop.map(|stop| request_builder.stop(stop));
+	// async-openai 0.10 has no `seed` request field, so `--seed` only fixes local selection;
+	// API outputs are reproducible only with temperature 0.
 	let request = request_builder.build().unwrap();
 
 	retry(ExponentialBackoff::default(), || async {
-		Ok(client.chat().create(request.clone()).await?)
+		client.chat().create(request.clone()).await.map_err(|e| {
+			if is_permanent(&e) {
+				backoff::Error::permanent(e)
+			} else {
+				backoff::Error::transient(e)
+			}
+		})
 	})
 	.await
 }
 
-pub async fn gpt(prompt: &str, model: Option<&str>, temperature: Option<f32>, max_tokens: Option<u16>, n: Option<isize>, stop: Option<&str>) -> Vec<String> {
-	let mut messages: Vec<ChatCompletionRequestMessage> = Vec::new();
-	messages.push(ChatCompletionRequestMessageArgs::default().role(Role::User).content(prompt).build().unwrap());
-	chatgpt(messages, model.unwrap_or("gpt_4"), temperature.unwrap_or(0.7), max_tokens.unwrap_or(1000), n.unwrap_or(1), stop).await
-}
-
-pub async fn chatgpt(messages: Vec<ChatCompletionRequestMessage>, model: &str, temperature: f32, max_tokens: u16, mut n: isize, stop: Option<&str>) -> Vec<String> {
+pub async fn chatgpt(messages: Vec<ChatCompletionRequestMessage>, model: &str, temperature: f32, max_tokens: u16, mut n: isize, stop: Option<&str>) -> anyhow::Result<Vec<String>> {
 	let mut outputs = Vec::new();
 	while n > 0 {
 		let cnt = n.min(20);
 		n -= cnt;
-		let res = completions_with_backoff(model, &messages, Some(temperature), Some(max_tokens), Some(cnt), stop).await.unwrap();
+		let res = match completions_with_backoff(model, &messages, Some(temperature), Some(max_tokens), Some(cnt), stop).await {
+			Ok(res) => res,
+			// includes `context_length_exceeded` when the token estimate was off, failing like any other prompt that does not fit
+			Err(e) => anyhow::bail!("Request to {} failed: {}", model, e),
+		};
 
 		outputs.extend(res.choices.iter().map(|choice| choice.message.content.to_owned()));
-        
+
 		// log completion tokens
-		unsafe {
-			COMPLETION_TOKENS += res.usage.clone().unwrap().completion_tokens;
-			PROMPT_TOKENS += res.usage.unwrap().prompt_tokens;
+		if let Some(usage) = res.usage {
+			let mut all_usage = USAGE.lock().unwrap();
+			let tokens = all_usage.entry(model.to_string()).or_default();
+			tokens.0 += usage.completion_tokens;
+			tokens.1 += usage.prompt_tokens;
 		}
 	}
-	outputs
+	Ok(outputs)
 }
 
-pub async fn gpt_usage(backend: &str) -> (u32, u32, f64) {
-	unsafe {
-		match backend {
-			"gpt-4" => {
-				let cost = (COMPLETION_TOKENS / 1000) as f64 * 0.06 + (PROMPT_TOKENS / 1000) as f64 * 0.03;
-				(COMPLETION_TOKENS, PROMPT_TOKENS, cost)
-			}
-			"gpt-3.5-turbo" => {
-				let cost = ((COMPLETION_TOKENS + PROMPT_TOKENS) / 1000) as f64 * 0.0002;
-				(COMPLETION_TOKENS, PROMPT_TOKENS, cost)
-			}
-			_ => panic!("Invalid backend"),
+fn cost(model: &str, completion_tokens: u32, prompt_tokens: u32) -> f64 {
+	match model {
+		"gpt-4" => completion_tokens as f64 / 1000.0 * 0.06 + prompt_tokens as f64 / 1000.0 * 0.03,
+		"gpt-3.5-turbo" => (completion_tokens + prompt_tokens) as f64 / 1000.0 * 0.0002,
+		_ => panic!("Invalid backend"),
+	}
+}
+
+/// Total (completion tokens, prompt tokens, cost) across every model used so far.
+pub async fn gpt_usage() -> (u32, u32, f64) {
+	USAGE.lock().unwrap().iter().fold((0, 0, 0f64), |(completion, prompt, total), (model, (c, p))| (completion + c, prompt + p, total + cost(model, *c, *p)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn chat(turns: &[(Role, &str)]) -> Prompt {
+		turns.iter().fold(Prompt::chat(), |prompt, (role, content)| prompt.turn(role.clone(), content))
+	}
+
+	#[test]
+	fn counts_message_framing() {
+		let text = "Use numbers and basic arithmetic operations to obtain 24.";
+		assert_eq!(Prompt::Text(text.to_string()).tokens("gpt-4"), tokens::count("gpt-4", text) + 7);
+		let prompt = chat(&[(Role::System, "Be brief."), (Role::User, text)]);
+		assert_eq!(prompt.tokens("gpt-4"), 4 + tokens::count("gpt-4", "Be brief.") + 4 + tokens::count("gpt-4", text) + 3);
+		// a repeated role merges into the last turn
+		assert_eq!(chat(&[(Role::User, "a"), (Role::User, "b")]).messages().len(), 1);
+	}
+
+	#[test]
+	fn truncates_oldest_examples_first() {
+		let prompt = chat(&[
+			(Role::System, "Answer."),
+			(Role::User, "1 + 1"),
+			(Role::Assistant, "2"),
+			(Role::User, "2 + 2"),
+			(Role::Assistant, "4"),
+			(Role::User, "3 + 3"),
+		]);
+		let at_most = |turns: usize| move |prompt: &Prompt| prompt.clone().messages().len() <= turns;
+		let truncated = prompt.truncate_examples(at_most(4)).unwrap().messages();
+		assert_eq!(truncated.iter().map(|m| m.content.as_str()).collect::<Vec<_>>(), ["Answer.", "2 + 2", "4", "3 + 3"]);
+		let truncated = prompt.truncate_examples(at_most(2)).unwrap().messages();
+		assert_eq!(truncated.iter().map(|m| m.content.as_str()).collect::<Vec<_>>(), ["Answer.", "3 + 3"]);
+		assert_eq!(prompt.truncate_examples(at_most(1)), None);
+		assert_eq!(Prompt::Text("3 + 3".to_string()).truncate_examples(at_most(9)), None);
+	}
+
+	#[test]
+	fn truncate_examples_needs_chat_prompts() {
+		assert!(ContextPolicy::TruncateExamples.check(false).is_err());
+		assert!(ContextPolicy::TruncateExamples.check(true).is_ok());
+		assert!(ContextPolicy::Fail.check(false).is_ok());
+	}
+
+	#[tokio::test]
+	async fn fails_before_sending_prompts_that_do_not_fit() {
+		let long = Prompt::Text("24 ".repeat(context_window("gpt-4") * 2));
+		for policy in [ContextPolicy::Fail, ContextPolicy::TruncateExamples, ContextPolicy::DropCandidates] {
+			let model = ModelConfig::new("gpt-4", 0.7, 100, policy).unwrap();
+			assert!(!model.fits(&long));
+			let err = model.complete(&long, 1, None).await.err().unwrap();
+			assert!(err.to_string().contains("leaves no room for 100 completion tokens"), "{err}");
 		}
 	}
 }
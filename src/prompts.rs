@@ -0,0 +1,69 @@
+use crate::{
+	models::Prompt,
+	tasks::TaskOptions,
+	template::{Template, Vars},
+};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// Where prompt overrides are looked up without `--prompt_dir`. The directory is optional:
+/// the tree does not ship one, and tasks use their built-in prompts for every file it lacks.
+pub const DEFAULT_PROMPT_DIR: &str = "./prompts";
+
+/// The prompt templates of one task. Each built-in default can be replaced by a file
+/// `<prompt_dir>/<task>/<name>.txt`, so prompts can change without recompiling. The README lists
+/// each task's prompt names and the variables they can use.
+#[derive(Debug, Clone)]
+pub struct Prompts {
+	task: String,
+	templates: BTreeMap<String, Template>,
+	/// Send the templates' turns as chat messages instead of one flat string.
+	chat: bool,
+}
+
+impl Prompts {
+	pub fn load(task: &str, defaults: &[(&str, &str)], options: &TaskOptions) -> anyhow::Result<Prompts> {
+		let prompt_dir = options.prompt_dir.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_PROMPT_DIR));
+		let mut templates = BTreeMap::new();
+		for (name, default) in defaults {
+			let path = prompt_dir.join(task).join(format!("{name}.txt"));
+			let template = if path.is_file() {
+				let source = std::fs::read_to_string(&path)?;
+				Template::parse(&source).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?
+			} else {
+				Template::parse(default)?
+			};
+			templates.insert(name.to_string(), template);
+		}
+
+		Ok(Prompts {
+			task: task.to_string(),
+			templates,
+			chat: options.chat_prompts,
+		})
+	}
+
+	pub fn contains(&self, name: &str) -> bool {
+		self.templates.contains_key(name)
+	}
+
+	pub fn get(&self, name: &str) -> &Template {
+		self.templates.get(name).unwrap_or_else(|| panic!("No {name:?} prompt for task {:?}", self.task))
+	}
+
+	pub fn render(&self, name: &str, vars: &Vars) -> String {
+		self.get(name).render(vars)
+	}
+
+	/// Renders `name` as a flat prompt, or in chat mode as trimmed, non-empty turns.
+	pub fn prompt(&self, name: &str, vars: &Vars) -> Prompt {
+		if !self.chat {
+			return Prompt::Text(self.render(name, vars));
+		}
+		self.get(name)
+			.render_turns(vars)
+			.into_iter()
+			.map(|(role, content)| (role, content.trim().to_string()))
+			.filter(|(_, content)| !content.is_empty())
+			.fold(Prompt::chat(), |prompt, (role, content)| prompt.turn(role, &content))
+	}
+}
@@ -0,0 +1,180 @@
+use rand::{
+	distributions::{Distribution, WeightedIndex},
+	Rng,
+};
+
+/// Strategy for picking which candidates survive a search step.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum Selector {
+	/// Top-k by value; ties keep the earlier candidate.
+	Greedy,
+	/// k draws proportional to value, with replacement (the original ToT behaviour).
+	Sample,
+	/// k distinct candidates drawn proportional to value.
+	SampleWithoutReplacement,
+	/// k distinct candidates drawn from `softmax(value / temperature)`.
+	Softmax { temperature: f32 },
+	/// Each slot takes the best remaining candidate, or a uniformly random one with probability `epsilon`.
+	EpsilonGreedy { epsilon: f32 },
+}
+
+impl Selector {
+	pub fn from_args(method: &str, temperature: f32, epsilon: f32) -> anyhow::Result<Self> {
+		Ok(match method {
+			"greedy" => Selector::Greedy,
+			"sample" => Selector::Sample,
+			"sample_without_replacement" => Selector::SampleWithoutReplacement,
+			"softmax" => Selector::Softmax { temperature },
+			"epsilon_greedy" => Selector::EpsilonGreedy { epsilon },
+			method => anyhow::bail!("Invalid method_select: {:?}", method),
+		})
+	}
+
+	/// The `--method_select` value naming this strategy.
+	pub fn name(&self) -> &'static str {
+		match self {
+			Selector::Greedy => "greedy",
+			Selector::Sample => "sample",
+			Selector::SampleWithoutReplacement => "sample_without_replacement",
+			Selector::Softmax { .. } => "softmax",
+			Selector::EpsilonGreedy { .. } => "epsilon_greedy",
+		}
+	}
+
+	/// Returns the indices of the selected candidates.
+	pub fn select<R: Rng>(&self, values: &[f32], k: usize, rng: &mut R) -> Vec<usize> {
+		if values.is_empty() {
+			return vec![];
+		}
+		match self {
+			Selector::Greedy => ranked(values).into_iter().take(k).collect(),
+			Selector::Sample => {
+				let weights = values.iter().map(|v| v.max(0.0)).collect::<Vec<_>>();
+				match WeightedIndex::new(&weights) {
+					Ok(dist) => (0..k).map(|_| dist.sample(rng)).collect(),
+					// all-zero (or otherwise unusable) values: every candidate is equally good
+					Err(_) => (0..k).map(|_| rng.gen_range(0..values.len())).collect(),
+				}
+			}
+			Selector::SampleWithoutReplacement => sample_distinct(values.iter().map(|v| v.max(0.0)).collect(), k, rng),
+			Selector::Softmax { temperature } if *temperature <= 0.0 => Selector::Greedy.select(values, k, rng),
+			Selector::Softmax { temperature } => {
+				let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+				let weights = values.iter().map(|v| ((v - max) / temperature).exp()).map(|w| if w.is_nan() { 0.0 } else { w });
+				sample_distinct(weights.collect(), k, rng)
+			}
+			Selector::EpsilonGreedy { epsilon } => {
+				let mut remaining = ranked(values);
+				let mut selected = vec![];
+				while selected.len() < k && !remaining.is_empty() {
+					let pos = if rng.gen::<f32>() < *epsilon { rng.gen_range(0..remaining.len()) } else { 0 };
+					selected.push(remaining.remove(pos));
+				}
+				selected
+			}
+		}
+	}
+}
+
+/// Candidate indices ordered by descending value, stable for ties. NaN values rank last.
+fn ranked(values: &[f32]) -> Vec<usize> {
+	let value = |id: usize| if values[id].is_nan() { f32::NEG_INFINITY } else { values[id] };
+	let mut ids = (0..values.len()).collect::<Vec<_>>();
+	ids.sort_by(|a, b| value(*b).total_cmp(&value(*a)));
+	ids
+}
+
+fn sample_distinct<R: Rng>(weights: Vec<f32>, k: usize, rng: &mut R) -> Vec<usize> {
+	let mut remaining = (0..weights.len()).collect::<Vec<_>>();
+	let mut selected = vec![];
+	while selected.len() < k && !remaining.is_empty() {
+		let remaining_weights = remaining.iter().map(|id| weights[*id]).collect::<Vec<_>>();
+		let pos = match WeightedIndex::new(&remaining_weights) {
+			Ok(dist) => dist.sample(rng),
+			Err(_) => rng.gen_range(0..remaining.len()),
+		};
+		selected.push(remaining.remove(pos));
+	}
+	selected
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::{rngs::StdRng, SeedableRng};
+
+	const ALL: [Selector; 5] = [
+		Selector::Greedy,
+		Selector::Sample,
+		Selector::SampleWithoutReplacement,
+		Selector::Softmax { temperature: 1.0 },
+		Selector::EpsilonGreedy { epsilon: 0.5 },
+	];
+
+	#[test]
+	fn greedy_keeps_the_best_and_earlier_ties() {
+		let mut rng = StdRng::seed_from_u64(0);
+		assert_eq!(Selector::Greedy.select(&[1.0, 3.0, 2.0, 3.0], 3, &mut rng), [1, 3, 2]);
+		assert_eq!(Selector::Greedy.select(&[], 3, &mut rng), Vec::<usize>::new());
+	}
+
+	#[test]
+	fn nan_values_rank_last() {
+		let mut rng = StdRng::seed_from_u64(0);
+		let values = [1.0, f32::NAN, 2.0, f32::NEG_INFINITY];
+		assert_eq!(Selector::Greedy.select(&values, 4, &mut rng), [2, 0, 1, 3]);
+		assert_eq!(Selector::EpsilonGreedy { epsilon: 0.0 }.select(&values, 2, &mut rng), [2, 0]);
+		for _ in 0..50 {
+			assert!(!Selector::Softmax { temperature: 1.0 }.select(&[0.0, f32::NAN, 0.0], 2, &mut rng).contains(&1));
+			assert!(!Selector::SampleWithoutReplacement.select(&[1.0, f32::NAN, 1.0], 2, &mut rng).contains(&1));
+		}
+	}
+
+	#[test]
+	fn all_zero_values_are_drawn_uniformly() {
+		let mut rng = StdRng::seed_from_u64(1);
+		for selector in [Selector::Sample, Selector::SampleWithoutReplacement] {
+			let mut counts = [0; 4];
+			for _ in 0..400 {
+				for id in selector.select(&[0.0; 4], 1, &mut rng) {
+					counts[id] += 1;
+				}
+			}
+			assert!(counts.iter().all(|count| *count > 50), "{selector:?}: {counts:?}");
+		}
+	}
+
+	#[test]
+	fn sampling_without_replacement_never_repeats() {
+		let mut rng = StdRng::seed_from_u64(2);
+		for _ in 0..100 {
+			for selector in [Selector::SampleWithoutReplacement, Selector::Softmax { temperature: 0.5 }, Selector::EpsilonGreedy { epsilon: 0.5 }] {
+				let mut ids = selector.select(&[5.0, 0.0, 1.0, 1.0, 0.0], 10, &mut rng);
+				assert_eq!(ids.len(), 5);
+				ids.sort();
+				assert_eq!(ids, [0, 1, 2, 3, 4]);
+			}
+		}
+	}
+
+	#[test]
+	fn epsilon_zero_is_greedy() {
+		let mut rng = StdRng::seed_from_u64(3);
+		let values = [0.5, 2.0, 2.0, 7.0, 1.0];
+		for k in 0..=5 {
+			assert_eq!(Selector::EpsilonGreedy { epsilon: 0.0 }.select(&values, k, &mut rng), Selector::Greedy.select(&values, k, &mut rng));
+		}
+	}
+
+	#[test]
+	fn every_selector_returns_valid_indices() {
+		let mut rng = StdRng::seed_from_u64(4);
+		for selector in ALL {
+			let ids = selector.select(&[3.0, 1.0, 2.0], 2, &mut rng);
+			assert_eq!(ids.len(), 2, "{selector:?}");
+			assert!(ids.iter().all(|id| *id < 3), "{selector:?}");
+			assert_eq!(Selector::from_args(selector.name(), 1.0, 0.5).unwrap(), selector);
+		}
+	}
+}
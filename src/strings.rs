@@ -1,37 +1,7 @@
 // GAME24
-pub static VALUE_LAST_STEP_PROMPT: &'static str = r#"
-Use numbers and basic arithmetic operations (+ - * /) to obtain 24. Given an input and an answer, give a judgement (sure/impossible) if the answer is correct, i.e. it uses each input exactly once and no other numbers, and reach 24.
-Input: 4 4 6 8
-Answer: (4 + 8) * (6 - 4) = 24
-Judge:
-sure
-Input: 2 9 10 12
-Answer: 2 * 12 * (10 - 9) = 24
-Judge:
-sure
-Input: 4 9 10 13
-Answer: (13 - 9) * (10 - 4) = 24
-Judge:
-sure
-Input: 4 4 6 8
-Answer: (4 + 8) * (6 - 4) + 1 = 25
-Judge:
-impossible
-Input: 2 9 10 12
-Answer: 2 * (12 - 10) = 24
-Judge:
-impossible
-Input: 4 9 10 13
-Answer: (13 - 4) * (10 - 9) = 24
-Judge:
-impossible
-Input: {input}
-Answer: {ans}
-Judge:"#;
-
-pub static PROPOSE_PROMPT_GAME24: &'static str = r#"
-Input: 2 8 8 14
-Possible next steps:
+pub static PROPOSE_PROMPT_GAME24: &str = r#"
+{{@user}}Input: 2 8 8 14
+{{@assistant}}Possible next steps:
 2 + 8 = 10 (left: 8 10 14)
 8 / 2 = 4 (left: 4 8 14)
 14 + 2 = 16 (left: 8 8 16)
@@ -40,105 +10,255 @@ Possible next steps:
 14 - 8 = 6 (left: 2 6 8)
 14 /  2 = 7 (left: 7 8 8)
 14 - 2 = 12 (left: 8 8 12)
-Input: {input}
+{{@user}}Input: {{input}}
 Possible next steps:
 "#;
-pub static STANDARD_PROMPT_GAME24: &'static str = r#"
+pub static STANDARD_PROMPT_GAME24: &str = r#"{{@system}}
 Use numbers and basic arithmetic operations (+ - * /) to obtain 24.
-Input: 4 4 6 8
-Answer: (4 + 8) * (6 - 4) = 24
-Input: 2 9 10 12
-Answer: 2 * 12 * (10 - 9) = 24
-Input: 4 9 10 13
-Answer: (13 - 9) * (10 - 4) = 24
-Input: 1 4 8 8
-Answer: (8 / 4 + 1) * 8 = 24
-Input: 5 5 5 9
-Answer: 5 + 5 + 5 + 9 = 24
-Input: {input}
-"#;
-
-pub static COT_PROMPT_GAME24: &'static str = r#"
+{{@user}}Input: 4 4 6 8
+{{@assistant}}Answer: (4 + 8) * (6 - 4) = 24
+{{@user}}Input: 2 9 10 12
+{{@assistant}}Answer: 2 * 12 * (10 - 9) = 24
+{{@user}}Input: 4 9 10 13
+{{@assistant}}Answer: (13 - 9) * (10 - 4) = 24
+{{@user}}Input: 1 4 8 8
+{{@assistant}}Answer: (8 / 4 + 1) * 8 = 24
+{{@user}}Input: 5 5 5 9
+{{@assistant}}Answer: 5 + 5 + 5 + 9 = 24
+{{@user}}Input: {{input}}
+{{y}}"#;
+
+pub static COT_PROMPT_GAME24: &str = r#"{{@system}}
 Use numbers and basic arithmetic operations (+ - * /) to obtain 24. Each step, you are only allowed to choose two of the remaining numbers to obtain a new number.
-Input: 4 4 6 8
-Steps:
+{{@user}}Input: 4 4 6 8
+{{@assistant}}Steps:
 4 + 8 = 12 (left: 4 6 12)
 6 - 4 = 2 (left: 2 12)
 2 * 12 = 24 (left: 24)
 Answer: (6 - 4) * (4 + 8) = 24
-Input: 2 9 10 12
-Steps:
+{{@user}}Input: 2 9 10 12
+{{@assistant}}Steps:
 12 * 2 = 24 (left: 9 10 24)
 10 - 9 = 1 (left: 1 24)
 24 * 1 = 24 (left: 24)
 Answer: (12 * 2) * (10 - 9) = 24
-Input: 4 9 10 13
-Steps:
+{{@user}}Input: 4 9 10 13
+{{@assistant}}Steps:
 13 - 10 = 3 (left: 3 4 9)
 9 - 3 = 6 (left: 4 6)
 4 * 6 = 24 (left: 24)
 Answer: 4 * (9 - (13 - 10)) = 24
-Input: 1 4 8 8
-Steps:
+{{@user}}Input: 1 4 8 8
+{{@assistant}}Steps:
 8 / 4 = 2 (left: 1 2 8)
 1 + 2 = 3 (left: 3 8)
 3 * 8 = 24 (left: 24)
 Answer: (1 + 8 / 4) * 8 = 24
-Input: 5 5 5 9
-Steps:
+{{@user}}Input: 5 5 5 9
+{{@assistant}}Steps:
 5 + 5 = 10 (left: 5 9 10)
 10 + 5 = 15 (left: 9 15)
 15 + 9 = 24 (left: 24)
 Answer: ((5 + 5) + 5) + 9 = 24
-Input: {input}
-"#;
-pub static VALUE_PROMPT_GAME24: &'static str = r#"
+{{@user}}Input: {{input}}
+{{y}}"#;
+pub static VALUE_PROMPT_GAME24: &str = r#"{{#if is_last_step}}{{@system}}
+Use numbers and basic arithmetic operations (+ - * /) to obtain 24. Given an input and an answer, give a judgement (sure/impossible) if the answer is correct, i.e. it uses each input exactly once and no other numbers, and reach 24.
+{{@user}}Input: 4 4 6 8
+Answer: (4 + 8) * (6 - 4) = 24
+Judge:
+{{@assistant}}sure
+{{@user}}Input: 2 9 10 12
+Answer: 2 * 12 * (10 - 9) = 24
+Judge:
+{{@assistant}}sure
+{{@user}}Input: 4 9 10 13
+Answer: (13 - 9) * (10 - 4) = 24
+Judge:
+{{@assistant}}sure
+{{@user}}Input: 4 4 6 8
+Answer: (4 + 8) * (6 - 4) + 1 = 25
+Judge:
+{{@assistant}}impossible
+{{@user}}Input: 2 9 10 12
+Answer: 2 * (12 - 10) = 24
+Judge:
+{{@assistant}}impossible
+{{@user}}Input: 4 9 10 13
+Answer: (13 - 4) * (10 - 9) = 24
+Judge:
+{{@assistant}}impossible
+{{@user}}Input: {{input}}
+Answer: {{ans}}
+Judge:{{else}}{{@system}}
 Evaluate if given numbers can reach 24 (sure/likely/impossible)
-10 14
-10 + 14 = 24
+{{@user}}10 14
+{{@assistant}}10 + 14 = 24
 sure
-11 12
-11 + 12 = 23
+{{@user}}11 12
+{{@assistant}}11 + 12 = 23
 12 - 11 = 1
 11 * 12 = 132
 11 / 12 = 0.91
 impossible
-4 4 10
-4 + 4 + 10 = 8 + 10 = 18
+{{@user}}4 4 10
+{{@assistant}}4 + 4 + 10 = 8 + 10 = 18
 4 * 10 - 4 = 40 - 4 = 36
 (10 - 4) * 4 = 6 * 4 = 24
 sure
-4 9 11
-9 + 11 + 4 = 20 + 4 = 24
+{{@user}}4 9 11
+{{@assistant}}9 + 11 + 4 = 20 + 4 = 24
 sure
-5 7 8
-5 + 7 + 8 = 12 + 8 = 20
+{{@user}}5 7 8
+{{@assistant}}5 + 7 + 8 = 12 + 8 = 20
 (8 - 5) * 7 = 3 * 7 = 21
 I cannot obtain 24 now, but numbers are within a reasonable range
 likely
-5 6 6
-5 + 6 + 6 = 17
+{{@user}}5 6 6
+{{@assistant}}5 + 6 + 6 = 17
 (6 - 5) * 6 = 1 * 6 = 6
 I cannot obtain 24 now, but numbers are within a reasonable range
 likely
-10 10 11
-10 + 10 + 11 = 31
+{{@user}}10 10 11
+{{@assistant}}10 + 10 + 11 = 31
 (11 - 10) * 10 = 10
 10 10 10 are all too big
 impossible
-1 3 3
-1 * 3 * 3 = 9
+{{@user}}1 3 3
+{{@assistant}}1 * 3 * 3 = 9
 (1 + 3) * 3 = 12
 1 3 3 are all too small
 impossible
-{input}
+{{@user}}{{numbers}}
+{{/if}}"#;
+
+// COUNTDOWN
+pub static PROPOSE_PROMPT_COUNTDOWN: &str = r#"{{@system}}
+Combine two of the input numbers with one of the operations {{operators}} into a new number.
+{{@user}}Input: 2 8 8 14
+{{@assistant}}Possible next steps:
+2 + 8 = 10 (left: 8 10 14)
+8 / 2 = 4 (left: 4 8 14)
+14 + 2 = 16 (left: 8 8 16)
+2 * 8 = 16 (left: 8 14 16)
+8 - 2 = 6 (left: 6 8 14)
+14 - 8 = 6 (left: 2 6 8)
+14 / 2 = 7 (left: 7 8 8)
+14 - 2 = 12 (left: 8 8 12)
+{{@user}}Input: {{input}}
+Possible next steps:
 "#;
+pub static STANDARD_PROMPT_COUNTDOWN: &str = r#"{{@system}}
+Use each input number exactly once and the operations {{operators}} to obtain the target.
+{{@user}}Input: 4 4 6 8, target 24
+{{@assistant}}Answer: (4 + 8) * (6 - 4) = 24
+{{@user}}Input: 3 4 5 10, target 62
+{{@assistant}}Answer: 3 * 4 + 5 * 10 = 62
+{{@user}}Input: 4 6 9 25, target 79
+{{@assistant}}Answer: 4 + 25 * (9 - 6) = 79
+{{@user}}Input: 2 5 6 8 8, target 100
+{{@assistant}}Answer: 2 * 5 * (8 + 8 - 6) = 100
+{{@user}}Input: 3 6 25 50 75 100, target 952
+{{@assistant}}Answer: (3 * 75 * (6 + 100) - 50) / 25 = 952
+{{@user}}Input: {{input}}
+{{y}}"#;
+pub static COT_PROMPT_COUNTDOWN: &str = r#"{{@system}}
+Use each input number exactly once and the operations {{operators}} to obtain the target. Each step, you are only allowed to choose two of the remaining numbers to obtain a new number.
+{{@user}}Input: 4 4 6 8, target 24
+{{@assistant}}Steps:
+4 + 8 = 12 (left: 4 6 12)
+6 - 4 = 2 (left: 2 12)
+2 * 12 = 24 (left: 24)
+Answer: (6 - 4) * (4 + 8) = 24
+{{@user}}Input: 3 4 5 10, target 62
+{{@assistant}}Steps:
+3 * 4 = 12 (left: 5 10 12)
+5 * 10 = 50 (left: 12 50)
+12 + 50 = 62 (left: 62)
+Answer: 3 * 4 + 5 * 10 = 62
+{{@user}}Input: 4 6 9 25, target 79
+{{@assistant}}Steps:
+9 - 6 = 3 (left: 3 4 25)
+25 * 3 = 75 (left: 4 75)
+4 + 75 = 79 (left: 79)
+Answer: 4 + 25 * (9 - 6) = 79
+{{@user}}Input: 2 5 6 8 8, target 100
+{{@assistant}}Steps:
+2 * 5 = 10 (left: 6 8 8 10)
+8 - 6 = 2 (left: 2 8 10)
+8 + 2 = 10 (left: 10 10)
+10 * 10 = 100 (left: 100)
+Answer: 2 * 5 * (8 + 8 - 6) = 100
+{{@user}}Input: 3 6 25 50 75 100, target 952
+{{@assistant}}Steps:
+3 * 75 = 225 (left: 6 25 50 100 225)
+6 + 100 = 106 (left: 25 50 106 225)
+225 * 106 = 23850 (left: 25 50 23850)
+23850 - 50 = 23800 (left: 25 23800)
+23800 / 25 = 952 (left: 952)
+Answer: (3 * 75 * (6 + 100) - 50) / 25 = 952
+{{@user}}Input: {{input}}
+{{y}}"#;
+pub static VALUE_PROMPT_COUNTDOWN: &str = r#"{{#if is_last_step}}{{@system}}
+Use each input number exactly once and the operations {{operators}} to obtain the target. Given an input and an answer, give a judgement (sure/impossible) if the answer is correct, i.e. it uses each input exactly once and no other numbers, and reaches the target.
+{{@user}}Input: 3 4 5 10, target 62
+Answer: 3 * 4 + 5 * 10 = 62
+Judge:
+{{@assistant}}sure
+{{@user}}Input: 4 6 9 25, target 79
+Answer: 4 + 25 * (9 - 6) = 79
+Judge:
+{{@assistant}}sure
+{{@user}}Input: 3 4 5 10, target 62
+Answer: 4 * 5 + 10 * 4 = 60
+Judge:
+{{@assistant}}impossible
+{{@user}}Input: 4 6 9 25, target 79
+Answer: 25 * 3 + 4 = 79
+Judge:
+{{@assistant}}impossible
+{{@user}}Input: {{input}}
+Answer: {{ans}}
+Judge:{{else}}{{@system}}
+Evaluate if given numbers can reach the target (sure/likely/impossible)
+{{@user}}10 14, target 24
+{{@assistant}}10 + 14 = 24
+sure
+{{@user}}11 12, target 24
+{{@assistant}}11 + 12 = 23
+12 - 11 = 1
+11 * 12 = 132
+11 / 12 = 0.91
+impossible
+{{@user}}4 75, target 79
+{{@assistant}}4 + 75 = 79
+sure
+{{@user}}5 10 12, target 62
+{{@assistant}}5 * 10 + 12 = 50 + 12 = 62
+sure
+{{@user}}5 7 8, target 24
+{{@assistant}}5 + 7 + 8 = 12 + 8 = 20
+(8 - 5) * 7 = 3 * 7 = 21
+I cannot obtain 24 now, but numbers are within a reasonable range
+likely
+{{@user}}25 50 106 225, target 952
+{{@assistant}}225 * 4 = 900, but 4 is not left
+106 * 9 = 954, but 9 is not left
+I cannot obtain 952 now, but 225 * 106 is large and 25 and 50 can bring it down
+likely
+{{@user}}2 3 6, target 500
+{{@assistant}}2 * 3 * 6 = 36
+(2 + 3) * 6 = 30
+2 3 6 are all too small
+impossible
+{{@user}}{{numbers}}, target {{target}}
+{{/if}}"#;
 
 // CROSSWORDS
-pub static STANDARD_PROMPT_CROSSWORDS: &'static str = r#"
-Solve 5x5 mini crosswords. Given an input of 5 horizontal clues and 5 vertical clues, generate an output of 5 rows, where each row is 5 letter separated by space.
+pub static STANDARD_PROMPT_CROSSWORDS: &str = r#"{{@system}}
+{{#if mini}}Solve 5x5 mini crosswords. Given an input of 5 horizontal clues and 5 vertical clues, generate an output of 5 rows, where each row is 5 letter separated by space.{{else}}Solve {{rows}}x{{cols}} crosswords. Given an input of the empty grid, where # is a black square, and its numbered horizontal (h) and vertical (v) clues, generate an output of {{rows}} rows, where each row is {{cols}} letters or # separated by space.{{/if}}
 
-Input:
+{{@user}}Input:
 h1. A lunar valley
 h2. A fatty oil
 h3. To entice
@@ -150,14 +270,14 @@ v3. An illicit lover; a European lake
 v4. To lisp
 v5. To come in
 
-Output:
+{{@assistant}}Output:
 R I L L E
 O L E I N
 T E M P T
 A B A S E
 L O N E R
 
-Input:
+{{@user}}Input:
 h1. One who saws
 h2. A fungus genus
 h3. An assessor
@@ -169,14 +289,14 @@ v3. A Timorese island
 v4. Excessive fluid accumulation
 v5. Dewy; roscid
 
-Output:
+{{@assistant}}Output:
 S A W E R
 U R E D O
 R A T E R
 G R A M A
 E A R A L
 
-Input:
+{{@user}}Input:
 h1. Dandruff; scum; the bull-trout
 h2. One who greets; to vacillate; a British river
 h3. A Turkish written decree
@@ -188,14 +308,14 @@ v3. Grape jam
 v4. A flatworm larva
 v5. Ore refuse; to prepare material for glass by heat
 
-Output:
+{{@assistant}}Output:
 S C U R F
 W A V E R
 I R A D E
 P E T I T
 E X E A T
 
-Input:
+{{@user}}Input:
 h1. Presented; revealed
 h2. An interjection expressing sorrow
 h3. Benefit; result
@@ -207,14 +327,14 @@ v3. To speak formally
 v4. To indite; to scribble
 v5. An insecticide
 
-Output:
+{{@assistant}}Output:
 S H O W N
 W I R R A
 A V A I L
 R E T T E
 T R E E D
 
-Input:
+{{@user}}Input:
 h1. Scald; an ancient Scandinavian bard
 h2. H2O; to irrigate
 h3. The companion to an "intro", a postscript or exit piece
@@ -226,23 +346,23 @@ v3. Mine refuse
 v4. The garden dormouse
 v5. Like a drone; humming
 
-Output:
+{{@assistant}}Output:
 S K A L D
 W A T E R
 O U T R O
 O R L O N
 P I E T Y
 
-Input:
-{input}
+{{@user}}Input:
+{{input}}
 
 Output:
-"#;
+{{y}}"#;
 
-pub static COT_PROMPT_CROSSWORDS: &'static str = r#"
-Solve 5x5 mini crosswords. Given an input of 5 horizontal clues and 5 vertical clues, generate thoughts about which 5-letter word fits each clue, then an output of 5 rows, where each row is 5 letter separated by space.
+pub static COT_PROMPT_CROSSWORDS: &str = r#"{{@system}}
+{{#if mini}}Solve 5x5 mini crosswords. Given an input of 5 horizontal clues and 5 vertical clues, generate thoughts about which 5-letter word fits each clue, then an output of 5 rows, where each row is 5 letter separated by space.{{else}}Solve {{rows}}x{{cols}} crosswords. Given an input of the empty grid, where # is a black square, and its numbered horizontal (h) and vertical (v) clues, generate thoughts about which word fits each clue, then an output of {{rows}} rows, where each row is {{cols}} letters or # separated by space.{{/if}}
 
-Input:
+{{@user}}Input:
 h1. A lunar valley
 h2. A fatty oil
 h3. To entice
@@ -254,7 +374,7 @@ v3. An illicit lover; a European lake
 v4. To lisp
 v5. To come in
 
-Thoughts:
+{{@assistant}}Thoughts:
 h1. A lunar valley: RILLE
 h2. A fatty oil: OLEIN
 h3. To entice: TEMPT
@@ -273,7 +393,7 @@ T E M P T
 A B A S E
 L O N E R
 
-Input:
+{{@user}}Input:
 h1. One who saws
 h2. A fungus genus
 h3. An assessor
@@ -285,7 +405,7 @@ v3. A Timorese island
 v4. Excessive fluid accumulation
 v5. Dewy; roscid
 
-Thoughts:
+{{@assistant}}Thoughts:
 h1. One who saws: SAWER
 h2. A fungus genus: UREDO
 h3. An assessor: RATER
@@ -304,7 +424,7 @@ R A T E R
 G R A M A
 E A R A L
 
-Input:
+{{@user}}Input:
 h1. Dandruff; scum; the bull-trout
 h2. One who greets; to vacillate; a British river
 h3. A Turkish written decree
@@ -316,7 +436,7 @@ v3. Grape jam
 v4. A flatworm larva
 v5. Ore refuse; to prepare material for glass by heat
 
-Thoughts:
+{{@assistant}}Thoughts:
 h1. Dandruff; scum; the bull-trout: SCURF
 h2. One who greets; to vacillate; a British river: WAVER
 h3. A Turkish written decree: IRADE
@@ -335,7 +455,7 @@ I R A D E
 P E T I T
 E X E A T
 
-Input:
+{{@user}}Input:
 h1. Presented; revealed
 h2. An interjection expressing sorrow
 h3. Benefit; result
@@ -347,7 +467,7 @@ v3. To speak formally
 v4. To indite; to scribble
 v5. An insecticide
 
-Thoughts:
+{{@assistant}}Thoughts:
 h1. Presented; revealed: SHOWN
 h2. An interjection expressing sorrow: WIRRA
 h3. Benefit; result: AVAIL
@@ -366,7 +486,7 @@ A V A I L
 R E T T E
 T R E E D
 
-Input:
+{{@user}}Input:
 h1. Scald; an ancient Scandinavian bard
 h2. H2O; to irrigate
 h3. The companion to an "intro", a postscript or exit piece
@@ -378,7 +498,7 @@ v3. Mine refuse
 v4. The garden dormouse
 v5. Like a drone; humming
 
-Thoughts:
+{{@assistant}}Thoughts:
 h1. Scald; an ancient Scandinavian bard: SKALD
 h2. H2O; to irrigate: WATER
 h3. The companion to an "intro", a postscript or exit piece: OUTRO
@@ -397,19 +517,19 @@ O U T R O
 O R L O N
 P I E T Y
 
-Input:
-{input}
-"#;
+{{@user}}Input:
+{{input}}
+{{y}}"#;
 
-pub static PROPOSE_PROMPT_CROSSWORDS: &'static str = r#"Let's play a 5 x 5 mini crossword, where each word should have exactly 5 letters.
+pub static PROPOSE_PROMPT_CROSSWORDS: &str = r#"{{#if mini}}Let's play a 5 x 5 mini crossword, where each word should have exactly 5 letters.{{else}}Let's play a {{rows}} x {{cols}} crossword, where # is a black square and each word should have exactly as many letters as its cells on the board.{{/if}}
 
-{input}
+{{input}}
 
 Given the current status, list all possible answers for unfilled or changed words, and your confidence levels (certain/high/medium/low), using the format "h1. apple (medium)". Use "certain" cautiously and only when you are 100% sure this is the correct word. You can list more then one possible answer for each word.
 "#;
 
-pub static VALUE_PROMPT_CROSSWORDS: &'static str =r#"
-Evaluate if there exists a five letter word of some meaning that fit some letter constraints (sure/maybe/impossible).
+pub static VALUE_PROMPT_CROSSWORDS: &str = r#"
+Evaluate if there exists a {{#if mini}}five letter {{/if}}word of some meaning that fit some letter constraints (sure/maybe/impossible).
 
 Incorrect; to injure: w _ o _ g
 The letter constraint is: 5 letters, letter 1 is w, letter 3 is o, letter 5 is g.
@@ -456,16 +576,16 @@ fluke (f l u k e): 5 letters, letter 1 is f, not w
 I cannot think of any words now. 4 letters are constrained, and it is extremely unlikely to have a word with pattern w r a k _ to mean "Chance; a parasitic worm; a fish"
 impossible
 
-{input}
+{{input}}
 "#;
 // TEXT
-pub static STANDARD_PROMPT_TEXT: &'static str = r#"
-Write a coherent passage of 4 short paragraphs. The end sentence of each paragraph must be: {input}
-"#;
+pub static STANDARD_PROMPT_TEXT: &str = r#"
+Write a coherent passage of 4 short paragraphs. The end sentence of each paragraph must be: {{input}}
+{{y}}"#;
 
 
-pub static COT_PROMPT_TEXT: &'static str = r#"
-Write a coherent passage of 4 short paragraphs. The end sentence of each paragraph must be: {input}
+pub static COT_PROMPT_TEXT: &str = r#"
+Write a coherent passage of 4 short paragraphs. The end sentence of each paragraph must be: {{input}}
 
 Make a plan then write. Your output should be of the following format:
 
@@ -473,16 +593,22 @@ Plan:
 Your plan here.
 
 Passage:
-Your passage here."#;
+Your passage here.{{y}}"#;
 
-pub static VOTE_PROMPT_TEXT:&'static str = r#"
+pub static VOTE_PROMPT_TEXT: &str = r#"{{@system}}
 Given an instruction and several choices, decide which choice is most promising. Analyze each choice in detail, then conclude in the last line "The best choice is {s}", where s the integer id of the choice.
-"#;
+{{@user}}{{#each choices}}Choice {{index}}:
+{{item}}
+{{/each}}"#;
 
-pub static COMPARE_PROMPT_TEXT:&'static str = r#"
+pub static COMPARE_PROMPT_TEXT: &str = r#"
 Briefly analyze the coherency of the following two passages. Conclude in the last line "The more coherent passage is 1", "The more coherent passage is 2", or "The two passages are similarly coherent".
 "#;
 
-pub static SCORE_PROMPT_TEXT:&'static str = r#"
+pub static SCORE_PROMPT_TEXT: &str = r#"{{@system}}
 Analyze the following passage, then at the last line conclude "Thus the coherency score is {s}", where s is an integer from 1 to 10.
-"#;
\ No newline at end of file
+{{@user}}{{passage}}"#;
+// CONTEXT
+pub static SUMMARISE_PROMPT: &str = r#"
+Summarise the following passage in at most half its length. Keep its structure, final sentences and any numbers, and output only the summary.
+{{passage}}"#;
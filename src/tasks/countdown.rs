@@ -0,0 +1,388 @@
+use super::{TOutput, TaskOptions, ToTTask, DATA_PATH};
+use crate::{
+	dataset::{DatasetFormat, DatasetSpec},
+	expr,
+	models::{ModelConfig, Prompt},
+	prompts::Prompts,
+	strings,
+	template::Vars,
+};
+use async_trait::async_trait;
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt,
+	path::Path,
+};
+
+/// Operations answers may use without an `operators` task param.
+const OPERATORS: &str = "+-*/";
+
+/// Numbers to combine, each exactly once, into the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+	pub numbers: Vec<i64>,
+	pub target: i64,
+}
+
+impl Instance {
+	/// Reads back the `4 9 10 13, target 24` inputs the task hands out.
+	pub fn parse(x: &str) -> Option<Instance> {
+		let (numbers, target) = x.trim().split_once(", target ")?;
+		Some(Instance {
+			numbers: numbers.split_whitespace().map(|n| n.parse().ok()).collect::<Option<_>>()?,
+			target: target.trim().parse().ok()?,
+		})
+	}
+
+	fn numbers_text(&self) -> String {
+		self.numbers.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(" ")
+	}
+}
+
+impl fmt::Display for Instance {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}, target {}", self.numbers_text(), self.target)
+	}
+}
+
+/// Game of 24 with any count of numbers, any target and a subset of `+ - * /`.
+#[derive(Debug, Clone)]
+pub struct Countdown {
+	data: Vec<Instance>,
+	ids: Vec<String>,
+	operators: Vec<char>,
+	steps: isize,
+	value_cache: BTreeMap<String, f32>,
+	prompts: Prompts,
+}
+
+const PROMPTS: [(&str, &str); 4] = [
+	("standard", strings::STANDARD_PROMPT_COUNTDOWN),
+	("cot", strings::COT_PROMPT_COUNTDOWN),
+	("propose", strings::PROPOSE_PROMPT_COUNTDOWN),
+	("value", strings::VALUE_PROMPT_COUNTDOWN),
+];
+
+/// The distinct operations of `operators`, like `+-*` or `+ - *`.
+fn parse_operators(operators: &str) -> anyhow::Result<Vec<char>> {
+	let mut parsed = vec![];
+	for c in operators.chars().filter(|c| !c.is_whitespace()) {
+		if !OPERATORS.contains(c) {
+			anyhow::bail!("Invalid operator {:?}, expected some of + - * /", c);
+		}
+		if !parsed.contains(&c) {
+			parsed.push(c);
+		}
+	}
+	if parsed.is_empty() {
+		anyhow::bail!("No operator given, expected some of + - * /");
+	}
+	Ok(parsed)
+}
+
+impl Countdown {
+	/// Loads puzzles from a CSV with `numbers` and `target` columns. The `target` task param replaces the targets
+	/// of all of them, and `operators`, like `+-*`, limits the operations answers may use.
+	pub fn new(options: &TaskOptions) -> anyhow::Result<Countdown> {
+		let spec = DatasetSpec {
+			path: "countdown.csv".to_string(),
+			format: Some(DatasetFormat::Csv),
+			input_field: "numbers".to_string(),
+			answer_field: Some("target".to_string()),
+			..Default::default()
+		};
+		let operators = parse_operators(&options.param::<String>("operators")?.unwrap_or_else(|| OPERATORS.to_string()))?;
+		let target = options.param::<i64>("target")?;
+		let items = options.dataset.load(spec, &options.file_path, &Path::new(DATA_PATH).join("countdown"))?;
+		let mut data = vec![];
+		let ids = items.iter().map(|item| item.id.clone()).collect();
+		for item in items {
+			let numbers = item.input.split(|c: char| c.is_whitespace() || c == ',').filter(|n| !n.is_empty()).map(str::parse).collect::<Result<Vec<i64>, _>>();
+			let numbers = numbers.map_err(|_| anyhow::anyhow!("Invalid numbers {:?} in puzzle {}", item.input, item.id))?;
+			if numbers.is_empty() {
+				anyhow::bail!("Puzzle {} has no numbers", item.id);
+			}
+			let target = match (target, item.answer.as_deref()) {
+				(Some(target), _) => target,
+				(None, Some(target)) => target.trim().parse().map_err(|_| anyhow::anyhow!("Invalid target {:?} in puzzle {}", target, item.id))?,
+				(None, None) => anyhow::bail!("Puzzle {} has no target, pass --task_param target=N", item.id),
+			};
+			data.push(Instance { numbers, target });
+		}
+
+		Ok(Countdown {
+			// one step per combination of two numbers, then the answer
+			steps: data.iter().map(|instance| instance.numbers.len()).max().unwrap_or(4) as isize,
+			data,
+			ids,
+			operators,
+			value_cache: BTreeMap::new(),
+			prompts: Prompts::load("countdown", &PROMPTS, options)?,
+		})
+	}
+
+	fn instance(&self, x: &str) -> anyhow::Result<Instance> {
+		Instance::parse(x).ok_or(anyhow::anyhow!("Invalid countdown input: {:?}", x))
+	}
+
+	/// The operations as the prompts list them, like `+ - * /`.
+	fn operators_text(&self) -> String {
+		self.operators.iter().map(|op| op.to_string()).collect::<Vec<_>>().join(" ")
+	}
+}
+
+fn get_current_number(y: &str) -> Option<&str> {
+	y.trim().lines().last().unwrap_or("").split("left: ").last().unwrap_or("").split(')').next()
+}
+
+/// An expression over all of `numbers` with only `operators` that evaluates to `target`, if there is one.
+pub fn solve(numbers: &[f64], target: f64, operators: &[char]) -> Option<String> {
+	let terms = numbers.iter().map(|n| (*n, n.to_string())).collect::<Vec<_>>();
+	let expression = search(terms, target, operators, &mut BTreeSet::new())?;
+	Some(expression.strip_prefix('(').and_then(|e| e.strip_suffix(')')).map_or(expression.clone(), str::to_string))
+}
+
+/// Depth-first search combining two terms at a time; `seen` holds the values of the states already ruled out.
+fn search(terms: Vec<(f64, String)>, target: f64, operators: &[char], seen: &mut BTreeSet<Vec<u64>>) -> Option<String> {
+	if let [(value, expression)] = terms.as_slice() {
+		return ((value - target).abs() < 1e-6).then(|| expression.clone());
+	}
+	let mut state = terms.iter().map(|(value, _)| value.to_bits()).collect::<Vec<_>>();
+	state.sort();
+	if !seen.insert(state) {
+		return None;
+	}
+	for i in 0..terms.len() {
+		for j in 0..terms.len() {
+			let ((a, ea), (b, eb)) = (&terms[i], &terms[j]);
+			for op in operators {
+				// + and * commute, so one order of the operands is enough
+				if i == j || (matches!(op, '+' | '*') && i > j) {
+					continue;
+				}
+				let value = match op {
+					'+' => a + b,
+					'-' => a - b,
+					'*' => a * b,
+					'/' if *b != 0.0 => a / b,
+					_ => continue,
+				};
+				let mut rest = terms.iter().enumerate().filter(|(k, _)| *k != i && *k != j).map(|(_, term)| term.clone()).collect::<Vec<_>>();
+				rest.push((value, format!("({ea} {op} {eb})")));
+				if let Some(expression) = search(rest, target, operators, seen) {
+					return Some(expression);
+				}
+			}
+		}
+	}
+	None
+}
+
+/// Checks that the last line of `output` uses exactly the puzzle's numbers, signs included, and only `operators`,
+/// and evaluates to the target.
+fn check_countdown(instance: &Instance, operators: &[char], output: &str) -> bool {
+	let expression = output.trim().lines().next_back().unwrap_or("").to_lowercase().replace("answer: ", "");
+	let expression = expression.split('=').next().unwrap_or("");
+	let Some((mut numbers, used)) = expr::operands(expression) else {
+		return false;
+	};
+	if used.iter().any(|op| !operators.contains(op)) {
+		return false;
+	}
+
+	let mut problem_numbers = instance.numbers.iter().map(|n| *n as f64).collect::<Vec<_>>();
+	numbers.sort_by(f64::total_cmp);
+	problem_numbers.sort_by(f64::total_cmp);
+	if numbers != problem_numbers {
+		return false;
+	}
+	expr::eval(expression).is_some_and(|value| (value - instance.target as f64).abs() < 1e-6)
+}
+
+#[async_trait]
+impl ToTTask for Countdown {
+	fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	fn get_steps(&self) -> isize {
+		self.steps
+	}
+
+	fn get_input(&mut self, idx: usize) -> anyhow::Result<String> {
+		self.data.get(idx).map(|instance| instance.to_string()).ok_or(anyhow::anyhow!("Item not found"))
+	}
+
+	fn get_id(&self, idx: usize) -> Option<String> {
+		self.ids.get(idx).cloned()
+	}
+
+	fn standard_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		self.prompts.prompt("standard", &Vars::new().text("operators", &self.operators_text()).text("input", x).text("y", y))
+	}
+
+	fn cot_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		self.prompts.prompt("cot", &Vars::new().text("operators", &self.operators_text()).text("input", x).text("y", y))
+	}
+
+	fn propose_prompt_wrap(&mut self, x: &str, y: &str) -> anyhow::Result<Prompt> {
+		let instance = self.instance(x)?;
+		let current_numbers = if y.is_empty() { instance.numbers_text() } else { get_current_number(y).unwrap_or("").to_string() };
+		let prompt = if current_numbers.trim() == instance.target.to_string() {
+			self.cot_prompt_wrap(x, &format!("Steps:{y}"))
+		} else {
+			self.prompts.prompt("propose", &Vars::new().text("operators", &self.operators_text()).text("input", &current_numbers))
+		};
+
+		Ok(prompt)
+	}
+
+	async fn get_value(&mut self, x: &str, y: &str, model: &ModelConfig, n_evaluate_sample: isize, cache_value: bool) -> anyhow::Result<f32> {
+		let instance = self.instance(x)?;
+		let last_line = y.trim().lines().last().unwrap_or("");
+		let is_last_step = !last_line.contains("left: ");
+		let ans = last_line.to_lowercase().replace("answer: ", "");
+		let current_numbers = if is_last_step { "" } else { get_current_number(y).unwrap_or("") };
+		let (operators, target) = (self.operators_text(), instance.target.to_string());
+		let vars = Vars::new()
+			.flag("is_last_step", is_last_step)
+			.text("operators", &operators)
+			.text("input", x)
+			.text("ans", &ans)
+			.text("numbers", current_numbers)
+			.text("target", &target);
+		let value_prompt = self.prompts.prompt("value", &vars);
+		let cache_key = value_prompt.to_string();
+
+		if cache_value && self.value_cache.contains_key(&cache_key) {
+			return self.value_cache.get(&cache_key).ok_or(anyhow::anyhow!("Value not found in cache")).cloned();
+		}
+
+		// numbers the solver cannot bring to the target are not worth asking the model about
+		let left = current_numbers.split_whitespace().map(|n| n.parse::<f64>().ok()).collect::<Option<Vec<_>>>();
+		let unreachable = !is_last_step && left.is_some_and(|left| !left.is_empty() && solve(&left, instance.target as f64, &self.operators).is_none());
+		let value = if unreachable || (y.trim().lines().count() == instance.numbers.len() && !y.to_lowercase().contains("answer")) {
+			0f32
+		} else {
+			let outputs = model.complete(&value_prompt, n_evaluate_sample, None).await?;
+			let value_names = outputs.iter().map(|v| v.lines().last().unwrap_or("")).collect::<Vec<_>>();
+			[("sure", 20f32), ("likely", 1f32), ("impossible", 0.001f32)]
+				.iter()
+				.map(|(name, value)| value * value_names.iter().filter(|n| *n == name).count() as f32)
+				.sum()
+		};
+
+		if cache_value {
+			self.value_cache.insert(cache_key, value);
+		}
+
+		Ok(value)
+	}
+
+	async fn test_output(&mut self, idx: usize, output: &str, _scorer: &ModelConfig) -> anyhow::Result<TOutput> {
+		let mut result = TOutput::new();
+		let instance = self.data.get(idx).ok_or(anyhow::anyhow!("Item not found"))?;
+		result.r_game = check_countdown(instance, &self.operators, output);
+		result.r = if result.r_game { 1.0 } else { 0.0 };
+		result.correct = Some(result.r_game);
+		Ok(result)
+	}
+
+	async fn is_solved(&mut self, idx: usize, y: &str) -> bool {
+		let last_line = y.trim().lines().next_back().unwrap_or("").to_lowercase();
+		last_line.starts_with("answer") && self.data.get(idx).is_some_and(|instance| check_countdown(instance, &self.operators, y))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ALL: [char; 4] = ['+', '-', '*', '/'];
+
+	fn instance(numbers: &[i64], target: i64) -> Instance {
+		Instance { numbers: numbers.to_vec(), target }
+	}
+
+	#[test]
+	fn instances_round_trip() {
+		let parsed = Instance::parse("4 9 10 13, target 24").unwrap();
+		assert_eq!(parsed, instance(&[4, 9, 10, 13], 24));
+		assert_eq!(parsed.to_string(), "4 9 10 13, target 24");
+		assert_eq!(Instance::parse(" -3 8, target -24 "), Some(instance(&[-3, 8], -24)));
+		assert_eq!(Instance::parse("4 9 10 13"), None);
+		assert_eq!(Instance::parse("4 nine, target 24"), None);
+		assert_eq!(Instance::parse("4 9, target many"), None);
+	}
+
+	#[test]
+	fn solves_what_can_be_solved() {
+		let solution = solve(&[4.0, 9.0, 10.0, 13.0], 24.0, &ALL).unwrap();
+		assert_eq!(expr::eval(&solution), Some(24.0));
+		assert!(check_countdown(&instance(&[4, 9, 10, 13], 24), &ALL, &solution), "{solution}");
+		assert_eq!(solve(&[1.0, 1.0, 1.0, 1.0], 24.0, &ALL), None);
+		assert_eq!(solve(&[24.0], 24.0, &ALL), Some("24".to_string()));
+	}
+
+	#[test]
+	fn solves_with_the_allowed_operators_only() {
+		assert_eq!(solve(&[4.0, 6.0], 24.0, &['+', '-']), None);
+		assert_eq!(solve(&[4.0, 6.0], 24.0, &['*']), Some("4 * 6".to_string()));
+		let solution = solve(&[2.0, 3.0, 4.0], 20.0, &['+', '*']).unwrap();
+		assert!(!solution.contains(['-', '/']), "{solution}");
+		assert_eq!(expr::eval(&solution), Some(20.0));
+	}
+
+	#[test]
+	fn solves_through_fractions() {
+		// 8 / (3 - 8 / 3) is the only way to 24
+		let solution = solve(&[3.0, 3.0, 8.0, 8.0], 24.0, &ALL).unwrap();
+		assert!((expr::eval(&solution).unwrap() - 24.0).abs() < 1e-9, "{solution}");
+		assert_eq!(solve(&[3.0, 3.0, 8.0, 8.0], 24.0, &['+', '-', '*']), None);
+	}
+
+	#[test]
+	fn checks_answers() {
+		let puzzle = instance(&[4, 9, 10, 13], 24);
+		assert!(check_countdown(&puzzle, &ALL, "13 - 9 = 4 (left: 4 4 10)\n10 - 4 = 6 (left: 4 6)\nAnswer: (13 - 9) * (10 - 4) = 24"));
+		assert!(!check_countdown(&puzzle, &ALL, "Answer: (13 - 9) * (10 + 4) = 24"));
+		assert!(!check_countdown(&puzzle, &ALL, "Answer: (13 - 9) * (10 - 4"));
+		// a number left out, or used twice
+		assert!(!check_countdown(&puzzle, &ALL, "Answer: 4 * (13 - 10 + 3) = 24"));
+		assert!(!check_countdown(&puzzle, &ALL, "Answer: (13 - 9) * (10 - 4) + 4 - 4 = 24"));
+		// an operator the puzzle rules out
+		assert!(!check_countdown(&puzzle, &['+', '*'], "Answer: (13 - 9) * (10 - 4) = 24"));
+		// non-integer steps are fine
+		assert!(check_countdown(&instance(&[3, 3, 8, 8], 24), &ALL, "Answer: 8 / (3 - 8 / 3) = 24"));
+	}
+
+	#[test]
+	fn checks_negative_numbers() {
+		let puzzle = instance(&[-3, 8], -24);
+		assert!(check_countdown(&puzzle, &['*'], "Answer: -3 * 8 = -24"));
+		assert!(check_countdown(&puzzle, &['*'], "Answer: 8 * -3"));
+		// the sign of a number is not an operator, but negating a group is
+		assert!(!check_countdown(&puzzle, &['*'], "Answer: -(3 * 8)"));
+		assert!(!check_countdown(&instance(&[3, 8], 24), &ALL, "Answer: -3 * -8"));
+		assert!(!check_countdown(&instance(&[3, 8], -24), &ALL, "Answer: -3 * 8"));
+	}
+
+	#[test]
+	fn reads_target_and_operators_params() {
+		assert_eq!(parse_operators("+ - * /").unwrap(), ALL);
+		assert_eq!(parse_operators("*+*").unwrap(), ['*', '+']);
+		assert!(parse_operators("+^").is_err());
+		assert!(parse_operators(" ").is_err());
+
+		let countdown = Countdown::new(&TaskOptions::default()).unwrap();
+		assert_eq!((countdown.data[0].target, countdown.operators.as_slice()), (62, ALL.as_slice()));
+		let mut options = TaskOptions::default();
+		options.extra.insert("target".to_string(), "100".to_string());
+		options.extra.insert("operators".to_string(), "+*".to_string());
+		let countdown = Countdown::new(&options).unwrap();
+		assert!(countdown.data.iter().all(|instance| instance.target == 100));
+		assert_eq!(countdown.operators_text(), "+ *");
+		options.extra.insert("target".to_string(), "lots".to_string());
+		assert!(Countdown::new(&options).is_err());
+	}
+}
@@ -0,0 +1,762 @@
+use super::{TOutput, TaskOptions, ToTTask, DATA_PATH};
+use crate::{
+	dataset::{DatasetFormat, DatasetSpec},
+	models::{ModelConfig, Prompt},
+	prompts::Prompts,
+	strings,
+	template::Vars,
+};
+use async_trait::async_trait;
+use regex::Regex;
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	sync::LazyLock,
+};
+
+mod puzzle;
+
+pub use puzzle::{Puzzle, Word, BLACK};
+
+#[derive(Debug, Clone)]
+pub struct MiniCrossword {
+	env: MiniCrosswordEnv,
+	xs: Vec<String>,
+	ids: Vec<String>,
+	steps: isize,
+	prompts: Prompts,
+	words: Option<WordList>,
+	/// Verdict samples of each value prompt, so a word pattern shared by several candidates is asked about once.
+	value_cache: BTreeMap<String, Vec<String>>,
+}
+
+const PROMPTS: [(&str, &str); 4] = [
+	("standard", strings::STANDARD_PROMPT_CROSSWORDS),
+	("cot", strings::COT_PROMPT_CROSSWORDS),
+	("propose", strings::PROPOSE_PROMPT_CROSSWORDS),
+	("value", strings::VALUE_PROMPT_CROSSWORDS),
+];
+
+/// Words that board patterns are checked against before asking the model about them. The task ships
+/// none; `--task_param word_list=<file>` names a file with one word per line.
+#[derive(Debug, Clone)]
+pub struct WordList {
+	words: Vec<String>,
+}
+
+impl WordList {
+	/// Reads the words of `path` that have two letters or more, uppercased.
+	pub fn load(path: &Path) -> anyhow::Result<WordList> {
+		let content = std::fs::read_to_string(path).map_err(|e| anyhow::anyhow!("Cannot read word list {}: {}", path.display(), e))?;
+		let mut words = content.lines().map(|word| word.trim().to_uppercase()).filter(|word| word.chars().count() >= 2 && word.chars().all(char::is_alphabetic)).collect::<Vec<_>>();
+		words.sort();
+		words.dedup();
+		Ok(WordList { words })
+	}
+
+	/// Whether some word fits `pattern`, where `_` stands for any letter.
+	pub fn matches(&self, pattern: &str) -> bool {
+		let length = pattern.chars().count();
+		self.words.iter().any(|word| word.chars().count() == length && word.chars().zip(pattern.chars()).all(|(letter, p)| p == '_' || p == letter))
+	}
+}
+
+impl MiniCrossword {
+	pub fn new(options: &TaskOptions) -> anyhow::Result<MiniCrossword> {
+		let prompts = Prompts::load("crosswords", &PROMPTS, options)?;
+		let spec = DatasetSpec {
+			path: "mini0505.json".to_string(),
+			format: Some(DatasetFormat::Json),
+			input_field: "grid".to_string(),
+			id_field: Some("id".to_string()),
+			..Default::default()
+		};
+		let items = options.dataset.load(spec, &options.file_path, &Path::new(DATA_PATH).join("crosswords"))?;
+		let puzzles = items.iter().map(|item| Puzzle::from_record(&item.record).map_err(|e| anyhow::anyhow!("Invalid crossword {}: {}", item.id, e))).collect::<anyhow::Result<Vec<_>>>()?;
+		// every word may take a step, and the longest puzzle sets the depth
+		let steps = puzzles.iter().map(|puzzle| puzzle.words.len()).max().unwrap_or(10);
+		let mut env = MiniCrosswordEnv::new(puzzles);
+		let mut xs = vec![];
+
+		for idx in 0..env.n {
+			env.reset(idx)?;
+			xs.push(env.render_input());
+		}
+
+		let words = match options.param::<PathBuf>("word_list")? {
+			Some(path) if !path.is_file() => anyhow::bail!("Word list {} not found, word_list must name a file with one word per line", path.display()),
+			path => path.map(|path| WordList::load(&path)).transpose()?,
+		};
+
+		Ok(MiniCrossword {
+			env,
+			xs,
+			ids: items.into_iter().map(|item| item.id).collect(),
+			steps: steps as isize,
+			prompts,
+			words,
+			value_cache: BTreeMap::new(),
+		})
+	}
+
+	/// Plays `moves` on an empty board of puzzle `idx`. Unlike the actions of a search candidate, an invalid move is an error.
+	pub fn replay(&mut self, idx: usize, moves: &[String]) -> anyhow::Result<Position> {
+		let mut position = Position {
+			render: self.env.reset(idx)?,
+			rewards: TOutput::new(),
+		};
+		for (i, action) in moves.iter().enumerate() {
+			let out = self.env.step(action).map_err(|e| anyhow::anyhow!("Move {} ({}): {}", i + 1, action, e))?;
+			position = Position { render: out.render, rewards: out.letter };
+		}
+		Ok(position)
+	}
+
+	/// Resets the board of input `x` and plays the actions of `y` on it.
+	fn set_status(&mut self, x: &str, y: &str) -> anyhow::Result<TOutput> {
+		let Some(idx) = self.xs.iter().position(|val| val == x) else {
+			anyhow::bail!("Item not found");
+		};
+		self.play(idx, y)
+	}
+
+	/// Resets puzzle `idx` and plays the actions of `y` on it, skipping invalid ones; the rewards are those after the last valid action.
+	fn play(&mut self, idx: usize, y: &str) -> anyhow::Result<TOutput> {
+		self.env.reset(idx)?;
+		let mut info = TOutput::new();
+		for action in self.env.actions(y) {
+			if let Ok(out) = self.env.step(&action) {
+				info = out.letter;
+			}
+		}
+		Ok(info)
+	}
+
+	/// Template variables describing the grid of input `x`; inputs that are no puzzle of the dataset are taken for minis.
+	fn shape(&self, x: &str) -> (bool, String, String) {
+		match self.xs.iter().position(|val| val == x).map(|idx| &self.env.puzzles[idx]) {
+			Some(puzzle) => (puzzle.is_mini(), puzzle.rows.to_string(), puzzle.cols.to_string()),
+			None => (true, "5".to_string(), "5".to_string()),
+		}
+	}
+}
+
+/// A puzzle after some moves, for playing it by hand.
+#[derive(Debug, Clone)]
+pub struct Position {
+	/// The board, then every clue with its answer so far, grouped into unfilled, filled and changed words.
+	pub render: String,
+	/// Letter, word and game rewards; `r_game` is set once the board matches the solution.
+	pub rewards: TOutput,
+}
+
+/// How sure the model is of a proposed word, as the propose prompt asks it to say.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+	Low,
+	Medium,
+	High,
+	Certain,
+}
+
+impl Confidence {
+	fn parse(confidence: &str) -> Option<Confidence> {
+		Some(match confidence {
+			"certain" => Confidence::Certain,
+			"high" => Confidence::High,
+			"medium" => Confidence::Medium,
+			"low" => Confidence::Low,
+			_ => return None,
+		})
+	}
+
+	/// Weight of the level when proposals are ranked, as in the original crossword search.
+	pub fn score(self) -> f32 {
+		match self {
+			Confidence::Certain => 1.0,
+			Confidence::High => 0.5,
+			Confidence::Medium => 0.2,
+			Confidence::Low => 0.1,
+		}
+	}
+}
+
+impl std::fmt::Display for Confidence {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Confidence::Certain => "certain",
+			Confidence::High => "high",
+			Confidence::Medium => "medium",
+			Confidence::Low => "low",
+		})
+	}
+}
+
+/// A word the model proposes for one position, written back into thoughts as `h1. apple (medium)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proposal {
+	/// The label of a word of the puzzle, like `h1` or `v5`.
+	pub position: String,
+	/// Lowercase letters, as many as the word has cells.
+	pub word: String,
+	/// The highest confidence the word was given.
+	pub confidence: Confidence,
+	/// Sum of the confidence scores of every time it was proposed.
+	pub score: f32,
+}
+
+impl Proposal {
+	/// The board action placing the word.
+	pub fn action(&self) -> String {
+		format!("{}. {}", self.position, self.word)
+	}
+}
+
+impl std::fmt::Display for Proposal {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}. {} ({})", self.position, self.word, self.confidence)
+	}
+}
+
+/// A `h1. apple (medium)` line of a propose output.
+static PROPOSAL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^\s*([hv]\d+)\.\s*([a-z]+)\s*\((certain|high|medium|low)\)").unwrap());
+/// A `h1. apple` move of a proposal chain.
+static MOVE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*([hv]\d+)\. (\S+)").unwrap());
+
+/// The `h1. apple (medium)` lines of a propose output for `puzzle`, best first. Lines naming no word of the puzzle, with a word
+/// of another length or without a confidence level are skipped; a word proposed again for the same position adds up its scores.
+pub fn parse_proposals(output: &str, puzzle: &Puzzle) -> Vec<Proposal> {
+	let mut proposals: Vec<Proposal> = vec![];
+	for captures in output.lines().filter_map(|line| PROPOSAL.captures(line)) {
+		let position = captures[1].to_lowercase();
+		let word = captures[2].to_lowercase();
+		let Some(confidence) = Confidence::parse(&captures[3].to_lowercase()) else {
+			continue;
+		};
+		if puzzle.word(&position).is_none_or(|i| puzzle.words[i].cells.len() != word.chars().count()) {
+			continue;
+		}
+		match proposals.iter_mut().find(|proposal| proposal.position == position && proposal.word == word) {
+			Some(proposal) => {
+				proposal.score += confidence.score();
+				proposal.confidence = proposal.confidence.max(confidence);
+			}
+			None => proposals.push(Proposal {
+				position,
+				word,
+				confidence,
+				score: confidence.score(),
+			}),
+		}
+	}
+	// stable, so equally scored proposals keep the model's order
+	proposals.sort_by(|a, b| b.score.total_cmp(&a.score));
+	proposals
+}
+
+#[async_trait]
+impl ToTTask for MiniCrossword {
+	fn len(&self) -> usize {
+		self.env.n
+	}
+
+	fn get_steps(&self) -> isize {
+		self.steps
+	}
+
+	fn get_input(&mut self, idx: usize) -> anyhow::Result<String> {
+		self.env.reset(idx)?;
+		Ok(self.env.render_input())
+	}
+
+	fn get_id(&self, idx: usize) -> Option<String> {
+		self.ids.get(idx).cloned()
+	}
+
+	fn standard_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		let (mini, rows, cols) = self.shape(x);
+		self.prompts.prompt("standard", &Vars::new().flag("mini", mini).text("rows", &rows).text("cols", &cols).text("input", x).text("y", y))
+	}
+
+	fn cot_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		let (mini, rows, cols) = self.shape(x);
+		self.prompts.prompt("cot", &Vars::new().flag("mini", mini).text("rows", &rows).text("cols", &cols).text("input", x).text("y", y))
+	}
+
+	fn propose_prompt_wrap(&mut self, x: &str, y: &str) -> anyhow::Result<Prompt> {
+		self.set_status(x, y)?;
+		let (mini, rows, cols) = self.shape(x);
+		let board = self.env.render(true);
+		Ok(self.prompts.prompt("propose", &Vars::new().flag("mini", mini).text("rows", &rows).text("cols", &cols).text("input", &board)))
+	}
+
+	/// Keeps the `k` best [`parse_proposals`] of the model that do not overwrite a letter already on the board.
+	async fn get_proposals(&mut self, x: &str, y: &str, model: &ModelConfig, k: Option<usize>) -> anyhow::Result<Vec<String>> {
+		let propose_prompt = self.propose_prompt_wrap(x, y)?;
+		let output = model.complete(&propose_prompt, 1, None).await?;
+		let Some(outputs) = output.first() else {
+			anyhow::bail!("No outputs found");
+		};
+		Ok(parse_proposals(outputs, &self.env.ext.puzzle)
+			.into_iter()
+			.filter(|proposal| self.env.conflicts(&proposal.action()).is_ok_and(|conflicts| conflicts.is_empty()))
+			.take(k.unwrap_or(usize::MAX))
+			.map(|proposal| format!("{}{}\n", y, proposal))
+			.collect())
+	}
+
+	/// Asks whether each word with at least two letters placed can still be completed (sure/maybe/impossible), as the original
+	/// crossword search does. A candidate with an impossible word is worth 0; patterns the word list rules out never reach the model.
+	async fn get_value(&mut self, x: &str, y: &str, model: &ModelConfig, n_evaluate_sample: isize, cache_value: bool) -> anyhow::Result<f32> {
+		self.set_status(x, y)?;
+		let mini = self.env.ext.puzzle.is_mini();
+		let constrained = (0..self.env.ext.ans.len()).filter(|i| self.env.ext.ans[*i].chars().filter(|c| *c != '_').count() >= 2).collect::<Vec<_>>();
+		if let Some(words) = &self.words {
+			if constrained.iter().any(|i| !words.matches(&self.env.ext.ans[*i])) {
+				return Ok(0.0);
+			}
+		}
+		let mut value = 0.0;
+		for i in constrained {
+			let pattern = &self.env.ext.ans[i];
+			let letters = pattern.to_lowercase().chars().map(String::from).collect::<Vec<_>>().join(" ");
+			let input = format!("{}: {}", self.env.ext.puzzle.words[i].clue, letters);
+			let value_prompt = self.prompts.prompt("value", &Vars::new().flag("mini", mini).text("input", &input));
+			let cache_key = value_prompt.to_string();
+			let verdicts = match self.value_cache.get(&cache_key) {
+				Some(verdicts) if cache_value => verdicts.clone(),
+				_ => {
+					let outputs = model.complete(&value_prompt, n_evaluate_sample, None).await?;
+					let verdicts = outputs.iter().map(|output| output.trim().lines().last().unwrap_or("").trim().to_lowercase()).collect::<Vec<_>>();
+					if cache_value {
+						self.value_cache.insert(cache_key, verdicts.clone());
+					}
+					verdicts
+				}
+			};
+			let count = |verdict: &str| verdicts.iter().filter(|v| *v == verdict).count();
+			if count("impossible") > count("sure") + count("maybe") {
+				return Ok(0.0);
+			}
+			value += 20.0 * count("sure") as f32 + count("maybe") as f32;
+		}
+		Ok(value)
+	}
+
+	async fn test_output(&mut self, idx: usize, output: &str, _scorer: &ModelConfig) -> anyhow::Result<TOutput> {
+		let mut info = self.play(idx, output)?;
+		info.r = info.r_word;
+		info.correct = Some(info.r_word == 1.0);
+		Ok(info)
+	}
+
+	async fn is_solved(&mut self, idx: usize, y: &str) -> bool {
+		self.play(idx, y).is_ok_and(|info| info.r_game)
+	}
+}
+
+pub struct Out {
+	render: String,
+	letter: TOutput,
+}
+#[derive(Debug,Clone)]
+pub struct MiniCrosswordEnv {
+	puzzles: Vec<Puzzle>,
+	n: usize,
+	idx: Option<usize>,
+	ext: MiniCrosswordEnvExt,
+}
+
+/// A letter an action would write over a different one already on the board; cells count row by row from 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+	pub cell: usize,
+	pub placed: String,
+	pub proposed: String,
+}
+
+/// A board cell nobody has written yet.
+const BLANK: &str = "_";
+
+/// Whether a word was never written, placed by an action of its own, or had a placed letter overwritten by a crossing word.
+const UNFILLED: isize = 0;
+const FILLED: isize = 1;
+const CHANGED: isize = 2;
+
+impl MiniCrosswordEnv {
+	fn reset(&mut self, idx: usize) -> anyhow::Result<String> {
+		self.idx = Some(idx);
+
+		let puzzle = self.puzzles.get(idx).ok_or(anyhow::anyhow!("Item not found"))?;
+		self.ext.board_gt = puzzle.solution.clone();
+		self.ext.board = puzzle.solution.iter().map(|cell| if cell == BLACK { BLACK } else { BLANK }.to_string()).collect();
+		self.ext.puzzle = puzzle.clone();
+		self.ext.ans = self.get_ans(&self.ext.board);
+		self.ext.ans_gt = self.get_ans(&self.ext.board_gt);
+
+		self.ext.steps = 0;
+		self.ext.status = vec![UNFILLED; self.ext.puzzle.words.len()];
+
+		Ok(self.render(true))
+	}
+
+	/// The board with every answer, or with the answers grouped by status.
+	fn render(&self, status: bool) -> String {
+		let mut s = self.render_board();
+
+		if status {
+			s.push_str("\nUnfilled:\n");
+			s.push_str(&self.render_ans(Some(UNFILLED)));
+			s.push_str("\nFilled:\n");
+			s.push_str(&self.render_ans(Some(FILLED)));
+			s.push_str("\nChanged:\n");
+			s.push_str(&self.render_ans(Some(CHANGED)));
+			s
+		} else {
+			s.push('\n');
+			s.push_str(&self.render_ans(None));
+			s
+		}
+	}
+
+	/// The clue and current answer of every word, or of those with `status`.
+	fn render_ans(&self, status: Option<isize>) -> String {
+		let words = self.ext.puzzle.words.iter().enumerate();
+		words.filter(|(i, _)| status.is_none_or(|s| self.ext.status[*i] == s)).map(|(i, word)| format!("{}. {}: {}\n", word.label, word.clue, self.ext.ans[i])).collect()
+	}
+
+	fn render_board(&self) -> String {
+		self.ext.board.chunks(self.ext.puzzle.cols).fold("Current board:\n".to_string(), |acc, row| acc + &row.join("") + "\n")
+	}
+
+	/// Every clue, or those of the words with `status`.
+	fn render_clues(&self, status: Option<isize>) -> String {
+		let words = self.ext.puzzle.words.iter().enumerate();
+		words.filter(|(i, _)| status.is_none_or(|s| self.ext.status[*i] == s)).map(|(_, word)| format!("{}. {}\n", word.label, word.clue)).collect()
+	}
+
+	/// The puzzle as the model sees it: the clues of a mini, as in the prompt examples, and otherwise the empty grid first.
+	fn render_input(&self) -> String {
+		if self.ext.puzzle.is_mini() {
+			return self.render_clues(None);
+		}
+		let grid = self.ext.board.chunks(self.ext.puzzle.cols).map(|row| row.join(" ") + "\n").collect::<String>();
+		format!("Grid:\n{}\n{}", grid, self.render_clues(None))
+	}
+
+	/// The letters of every word on `board`, across words first.
+	fn get_ans(&self, board: &[String]) -> Vec<String> {
+		self.ext.puzzle.words.iter().map(|word| word.cells.iter().map(|cell| board[*cell].as_str()).collect()).collect()
+	}
+
+	/// Board actions described by a crossword candidate: the across words of a final `Output:` grid, then the down words
+	/// with a cell no across word covers, or the `h1. apple` style moves of a proposal chain.
+	fn actions(&self, y: &str) -> Vec<String> {
+		let Some((_, output)) = y.rsplit_once("Output:\n") else {
+			return y.lines().filter_map(|line| MOVE.captures(line)).map(|c| format!("{}. {}", &c[1], &c[2])).collect();
+		};
+		let (rows, cols) = (self.ext.puzzle.rows, self.ext.puzzle.cols);
+		let lines = output.trim().lines().collect::<Vec<_>>();
+		let lines = &lines[lines.len().saturating_sub(rows)..];
+		// letters may be separated by spaces; missing ones are left blank
+		let grid = lines.iter().map(|line| line.split(' ').take(cols).collect::<String>().chars().map(String::from).collect::<Vec<_>>()).collect::<Vec<_>>();
+		let letter = |cell: usize| grid[cell / cols].get(cell % cols).map_or(BLANK, String::as_str);
+		let (across, down): (Vec<_>, Vec<_>) = self.ext.puzzle.words.iter().partition(|word| word.label.starts_with('h'));
+		let covered = across.iter().flat_map(|word| word.cells.iter()).collect::<Vec<_>>();
+		let down = down.into_iter().filter(|word| word.cells.iter().any(|cell| !covered.contains(&cell)));
+		across
+			.iter()
+			.copied()
+			.chain(down)
+			.filter(|word| word.cells.iter().all(|cell| cell / cols < grid.len()))
+			.map(|word| format!("{}. {}", word.label, word.cells.iter().map(|cell| letter(*cell)).collect::<String>()))
+			.collect()
+	}
+
+	/// The word index of a `h1. apple` style action, the board cells it covers and the uppercase letters it writes there.
+	fn parse_action(&self, action: &str) -> anyhow::Result<(usize, Vec<usize>, Vec<String>)> {
+		let action = action.trim().lines().next_back().unwrap_or_default();
+		let Some((pos, word)) = action.split_once(". ") else {
+			anyhow::bail!("Invalid! Format should be like \"h1. apple\"")
+		};
+		let words = &self.ext.puzzle.words;
+		let Some(idx) = self.ext.puzzle.word(pos.trim()) else {
+			anyhow::bail!("Invalid! Position should be one of {}", words.iter().map(|word| word.label.as_str()).collect::<Vec<_>>().join(", "))
+		};
+		let letters = word.trim().chars().map(|c| c.to_uppercase().to_string()).collect::<Vec<_>>();
+		if letters.len() != words[idx].cells.len() {
+			anyhow::bail!("Invalid! Word should have {} letters.", words[idx].cells.len())
+		}
+		Ok((idx, words[idx].cells.clone(), letters))
+	}
+
+	/// Letters of `action` that differ from letters already on the board. Blanks in the action (`_`) never conflict.
+	pub fn conflicts(&self, action: &str) -> anyhow::Result<Vec<Conflict>> {
+		let (_, cells, letters) = self.parse_action(action)?;
+		Ok(cells
+			.into_iter()
+			.zip(letters)
+			.filter(|(cell, letter)| self.ext.board[*cell] != BLANK && letter != BLANK && self.ext.board[*cell] != *letter)
+			.map(|(cell, proposed)| Conflict {
+				cell,
+				placed: self.ext.board[cell].clone(),
+				proposed,
+			})
+			.collect())
+	}
+
+	/// Writes a `h1. apple` style action on the board. Invalid actions leave the board as it was but still count as a step.
+	fn step(&mut self, action: &str) -> anyhow::Result<Out> {
+		self.ext.steps += 1;
+		let (idx, cells, letters) = self.parse_action(action)?;
+		for (cell, letter) in cells.into_iter().zip(letters) {
+			self.ext.board[cell] = letter;
+		}
+
+		let new_ans = self.get_ans(&self.ext.board);
+		for (status, (ans, new_ans)) in self.ext.status.iter_mut().zip(self.ext.ans.iter().zip(&new_ans)) {
+			if ans.chars().zip(new_ans.chars()).any(|(letter, new_letter)| letter != new_letter && letter != '_') {
+				*status = CHANGED;
+			}
+		}
+		self.ext.status[idx] = FILLED;
+		self.ext.ans = new_ans;
+
+		let words = self.ext.ans.len();
+		let letters = self.ext.board.iter().zip(&self.ext.board_gt).filter(|(cell, gt)| cell == gt && *gt != BLACK).count();
+		Ok(Out {
+			render: self.render(true),
+			letter: TOutput {
+				r_letter: letters as f32 / self.ext.puzzle.white_cells() as f32,
+				r_word: self.ext.ans.iter().zip(&self.ext.ans_gt).filter(|(a, b)| a == b).count() as f32 / words as f32,
+				r_game: self.ext.board == self.ext.board_gt,
+				..TOutput::new()
+			},
+		})
+	}
+}
+
+#[derive(Default, Debug,Clone)]
+pub struct MiniCrosswordEnvExt {
+	puzzle: Puzzle,
+	board_gt: Vec<String>,
+	board: Vec<String>,
+	ans: Vec<String>,
+	ans_gt: Vec<String>,
+	steps: isize,
+	status: Vec<isize>,
+}
+
+impl MiniCrosswordEnv {
+	/// An environment over `puzzles`.
+	fn new(puzzles: Vec<Puzzle>) -> MiniCrosswordEnv {
+		let n = puzzles.len();
+
+		MiniCrosswordEnv {
+			puzzles,
+			n,
+			idx: None,
+			ext: Default::default(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn env() -> MiniCrosswordEnv {
+		let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(DATA_PATH).join("crosswords/mini0505.json");
+		let data: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+		let mut env = MiniCrosswordEnv::new(data.iter().map(|record| Puzzle::from_record(record).unwrap()).collect());
+		env.reset(0).unwrap();
+		env
+	}
+
+	fn row(env: &MiniCrosswordEnv, i: usize) -> String {
+		env.ext.board_gt[i * 5..(i + 1) * 5].concat()
+	}
+
+	fn column(env: &MiniCrosswordEnv, i: usize) -> String {
+		(0..5).map(|j| env.ext.board_gt[j * 5 + i].as_str()).collect()
+	}
+
+	#[test]
+	fn answers_are_rows_then_columns() {
+		let env = env();
+		assert_eq!(env.ext.ans_gt, ["AGEND", "MOTOR", "ARTSY", "SALLE", "SLEER", "AMASS", "GORAL", "ETTLE", "NOSLE", "DRYER"]);
+	}
+
+	#[test]
+	fn rows_fill_the_board() {
+		let mut env = env();
+		for i in 0..4 {
+			let out = env.step(&format!("h{}. {}", i + 1, row(&env, i))).unwrap();
+			assert!(!out.letter.r_game);
+			assert_eq!(out.letter.r_letter, (i + 1) as f32 * 5.0 / 25.0);
+			assert_eq!(out.letter.r_word, (i + 1) as f32 / 10.0);
+		}
+		let out = env.step(&format!("h5. {}", row(&env, 4))).unwrap();
+		assert!(out.letter.r_game);
+		assert_eq!((out.letter.r_letter, out.letter.r_word), (1.0, 1.0));
+		assert_eq!(env.ext.status, [FILLED, FILLED, FILLED, FILLED, FILLED, UNFILLED, UNFILLED, UNFILLED, UNFILLED, UNFILLED]);
+	}
+
+	#[test]
+	fn columns_fill_column_cells() {
+		let mut env = env();
+		let out = env.step(&format!("v2. {}", column(&env, 1))).unwrap();
+		for (cell, letter) in env.ext.board.iter().enumerate() {
+			assert_eq!(letter == BLANK, cell % 5 != 1, "cell {cell}");
+		}
+		assert_eq!(env.ext.ans[6], "GORAL");
+		assert_eq!(env.ext.ans[0], "_G___");
+		assert_eq!((out.letter.r_letter, out.letter.r_word), (0.2, 0.1));
+		assert_eq!(env.ext.status[6], FILLED);
+	}
+
+	#[test]
+	fn letters_are_uppercased() {
+		let mut env = env();
+		let out = env.step("h1. agEnd").unwrap();
+		assert_eq!(env.ext.ans[0], "AGEND");
+		assert_eq!(out.letter.r_word, 0.1);
+	}
+
+	#[test]
+	fn overwritten_letters_mark_words_changed() {
+		let mut env = env();
+		env.step("h1. XGEND").unwrap();
+		env.step("v2. GORAL").unwrap();
+		assert_eq!(env.ext.status[0], FILLED);
+		let out = env.step("v1. AMASS").unwrap();
+		assert_eq!(env.ext.status[0], CHANGED);
+		assert_eq!(env.ext.status[5], FILLED);
+		assert!(out.render.contains("Changed:\nh1. An agendum; something to be done: AGEND\n"));
+	}
+
+	#[test]
+	fn invalid_actions_leave_the_board() {
+		let mut env = env();
+		for action in ["h1 AGEND", "h1. AGENDA", "h6. AGEND", "x1. AGEND", "v0. AMASS", "hx. AGEND"] {
+			assert!(env.step(action).is_err(), "{action}");
+		}
+		assert!(env.ext.board.iter().all(|cell| cell == BLANK));
+		assert_eq!(env.ext.steps, 6);
+	}
+
+	#[test]
+	fn output_grids_are_scored_by_row() {
+		let mut env = env();
+		let actions = env.actions("Thoughts\nOutput:\nA G E N D\nM O T O R\nA R T S\nS A L L E\nS L E E R\n");
+		assert_eq!(actions[2], "h3. ARTS_");
+		let out = actions.iter().map(|action| env.step(action).unwrap()).last().unwrap();
+		assert_eq!((out.letter.r_letter, out.letter.r_word), (24.0 / 25.0, 0.8));
+	}
+
+	#[test]
+	fn conflicts_are_letters_that_differ() {
+		let mut env = env();
+		env.step("h1. AGEND").unwrap();
+		assert_eq!(env.conflicts("v1. AMASS").unwrap(), []);
+		assert_eq!(env.conflicts("v2. gOral").unwrap(), []);
+		assert_eq!(
+			env.conflicts("v3. STTLE").unwrap(),
+			[Conflict {
+				cell: 2,
+				placed: "E".to_string(),
+				proposed: "S".to_string()
+			}]
+		);
+		assert_eq!(env.conflicts("h1. _____").unwrap(), []);
+		assert!(env.conflicts("v6. AMASS").is_err());
+	}
+
+	#[test]
+	fn word_lists_match_patterns() {
+		let words = WordList {
+			words: vec!["AGEND".to_string(), "GORAL".to_string()],
+		};
+		assert!(words.matches("AG_ND"));
+		assert!(words.matches("_____"));
+		assert!(!words.matches("AM___"));
+	}
+
+	#[test]
+	fn word_lists_are_a_task_param() {
+		assert!(MiniCrossword::new(&TaskOptions::default()).unwrap().words.is_none());
+
+		let mut options = TaskOptions::default();
+		options.extra.insert("word_list".to_string(), "no-such-words.txt".to_string());
+		let err = MiniCrossword::new(&options).err().unwrap();
+		assert_eq!(err.to_string(), "Word list no-such-words.txt not found, word_list must name a file with one word per line");
+
+		let path = std::env::temp_dir().join(format!("crossword-words-{}.txt", std::process::id()));
+		std::fs::write(&path, "agend\nGoral\nx\nagend\n").unwrap();
+		options.extra.insert("word_list".to_string(), path.display().to_string());
+		let task = MiniCrossword::new(&options);
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(task.unwrap().words.unwrap().words, ["AGEND", "GORAL"]);
+	}
+
+	#[tokio::test]
+	async fn impossible_patterns_are_pruned_without_the_model() {
+		let mut task = MiniCrossword::new(&TaskOptions::default()).unwrap();
+		task.words = Some(WordList {
+			words: vec!["AGEND".to_string(), "MOTOR".to_string()],
+		});
+		let x = task.get_input(0).unwrap();
+		let model = ModelConfig::new("gpt-4", 0.7, 100, Default::default()).unwrap();
+		// the columns now read AM___, GO___ and so on, which no listed word fits
+		let value = task.get_value(&x, "h1. AGEND\nh2. MOTOR\n", &model, 1, true).await.unwrap();
+		assert_eq!(value, 0.0);
+	}
+
+	#[test]
+	fn proposals_are_validated_deduped_and_ranked() {
+		let output = "h1. agend (medium)\nv2. GORAL (High)\nh6. apple (certain)\nh2. motors (certain)\nh3. artsy\nv2. goral (low)\nh1. agend (medium)\n  v5. dryer (certain) because it fits\n";
+		let proposals = parse_proposals(output, &env().ext.puzzle);
+		let ranked = proposals.iter().map(|p| (p.to_string(), p.score)).collect::<Vec<_>>();
+		assert_eq!(ranked, [("v5. dryer (certain)".to_string(), 1.0), ("v2. goral (high)".to_string(), 0.6), ("h1. agend (medium)".to_string(), 0.4)]);
+		assert_eq!(proposals[1].action(), "v2. goral");
+	}
+
+	fn grid_env() -> MiniCrosswordEnv {
+		let record = serde_json::json!({
+			"grid": ["CAT#", "ABLE", "R#EL"],
+			"across": {"1": "Pet", "4": "Capable", "6": "Eel-like letters"},
+			"down": {"1": "Vehicle", "2": "Preposition", "3": "Story", "5": "Loose end"},
+		});
+		let mut env = MiniCrosswordEnv::new(vec![Puzzle::from_record(&record).unwrap()]);
+		env.reset(0).unwrap();
+		env
+	}
+
+	#[test]
+	fn grids_with_black_squares_score_white_cells() {
+		let mut env = grid_env();
+		assert_eq!(env.render_board(), "Current board:\n___#\n____\n_#__\n");
+		assert!(env.render_input().starts_with("Grid:\n_ _ _ #\n_ _ _ _\n_ # _ _\n\nh1. Pet\n"));
+		assert_eq!(env.step("h1. cats").err().unwrap().to_string(), "Invalid! Word should have 3 letters.");
+		assert!(env.step("h2. cat").err().unwrap().to_string().starts_with("Invalid! Position should be one of h1, h4, h6, v1"));
+
+		let out = env.step("v3. tle").unwrap();
+		assert_eq!((out.letter.r_letter, out.letter.r_word), (0.3, 1.0 / 7.0));
+		assert_eq!((env.ext.ans[5].as_str(), env.ext.ans[2].as_str()), ("TLE", "E_"));
+		for action in ["h1. cat", "h4. able", "h6. el", "v1. car", "v2. ab", "v5. el"] {
+			env.step(action).unwrap();
+		}
+		assert!(env.ext.board == env.ext.board_gt);
+		assert_eq!(env.ext.board[3], BLACK);
+	}
+
+	#[test]
+	fn output_grids_keep_black_squares() {
+		let mut env = grid_env();
+		let actions = env.actions("Output:\nC A T #\nA B L E\nR # E L\n");
+		assert_eq!(actions, ["h1. CAT", "h4. ABLE", "h6. EL", "v1. CAR"]);
+		let out = actions.iter().map(|action| env.step(action).unwrap()).last().unwrap();
+		assert!(out.letter.r_game);
+		assert_eq!(parse_proposals("h4. able (high)\nh4. abl (high)\nh9. cat (certain)\n", &env.ext.puzzle).len(), 1);
+	}
+}
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+/// A black square, in solutions and boards.
+pub const BLACK: &str = "#";
+
+/// One entry of a crossword: its label, clue and the cells it covers in reading order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+	/// `h` for across or `v` for down, then the clue number.
+	pub label: String,
+	pub clue: String,
+	/// Indices into the grid, row by row.
+	pub cells: Vec<usize>,
+}
+
+/// A crossword grid with its solution and words: across words first, then down words, each in clue order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Puzzle {
+	pub rows: usize,
+	pub cols: usize,
+	/// Uppercase letters, or [`BLACK`], row by row.
+	pub solution: Vec<String>,
+	pub words: Vec<Word>,
+}
+
+/// A puzzle in the numbered format: solution rows with `#` for black squares, and clues keyed by their number.
+/// Other fields, like an id or title, are ignored.
+#[derive(Debug, serde::Deserialize)]
+struct Numbered {
+	grid: Vec<String>,
+	across: BTreeMap<usize, String>,
+	down: BTreeMap<usize, String>,
+}
+
+impl Puzzle {
+	/// Reads one puzzle record, either
+	/// - `{"grid": ["AB#CD", ...], "across": {"1": clue, ...}, "down": {"1": clue, ...}}`, numbered the standard way:
+	///   every white cell that starts a word across or down gets the next number, row by row; or
+	/// - `[clues, cells]` as in `mini0505.json`: ten clues and the 25 letters of a 5x5 grid without black squares,
+	///   whose words are `h1`-`h5` by row and `v1`-`v5` by column.
+	pub fn from_record(record: &serde_json::Value) -> anyhow::Result<Puzzle> {
+		if record.is_array() {
+			let (clues, cells): (Vec<String>, Vec<String>) = serde_json::from_value(record.clone())?;
+			return Puzzle::mini(clues, cells);
+		}
+		Puzzle::numbered(serde_json::from_value(record.clone())?)
+	}
+
+	fn mini(clues: Vec<String>, cells: Vec<String>) -> anyhow::Result<Puzzle> {
+		if clues.len() != 10 || cells.len() != 25 {
+			anyhow::bail!("A 5x5 crossword should have 10 clues and 25 cells, found {} and {}", clues.len(), cells.len());
+		}
+		let cells_of = |i: usize| if i < 5 { (i * 5..(i + 1) * 5).collect() } else { (i - 5..25).step_by(5).collect() };
+		let words = clues
+			.into_iter()
+			.enumerate()
+			.map(|(i, clue)| Word {
+				label: if i < 5 { format!("h{}", i + 1) } else { format!("v{}", i - 4) },
+				clue,
+				cells: cells_of(i),
+			})
+			.collect();
+		Ok(Puzzle {
+			rows: 5,
+			cols: 5,
+			solution: cells.iter().map(|cell| cell.to_uppercase()).collect(),
+			words,
+		})
+	}
+
+	fn numbered(record: Numbered) -> anyhow::Result<Puzzle> {
+		// letters may be separated by spaces, as in model outputs
+		let grid = record.grid.iter().map(|row| row.split_whitespace().collect::<String>().to_uppercase()).collect::<Vec<_>>();
+		let rows = grid.len();
+		let cols = grid.first().map_or(0, |row| row.chars().count());
+		if rows == 0 || cols == 0 {
+			anyhow::bail!("The grid is empty");
+		}
+		if let Some(row) = grid.iter().position(|row| row.chars().count() != cols) {
+			anyhow::bail!("Row {} has {} cells, the first has {}", row + 1, grid[row].chars().count(), cols);
+		}
+		let solution = grid.iter().flat_map(|row| row.chars().map(String::from)).collect::<Vec<_>>();
+		if let Some(cell) = solution.iter().find(|cell| *cell != BLACK && !cell.chars().all(char::is_alphabetic)) {
+			anyhow::bail!("{:?} is neither a letter nor {}", cell, BLACK);
+		}
+
+		let white = |r: usize, c: usize| solution[r * cols + c] != BLACK;
+		let (mut across, mut down) = (vec![], vec![]);
+		let mut number = 0;
+		for r in 0..rows {
+			for c in 0..cols {
+				if !white(r, c) {
+					continue;
+				}
+				let starts_across = (c == 0 || !white(r, c - 1)) && c + 1 < cols && white(r, c + 1);
+				let starts_down = (r == 0 || !white(r - 1, c)) && r + 1 < rows && white(r + 1, c);
+				if !starts_across && !starts_down {
+					continue;
+				}
+				number += 1;
+				if starts_across {
+					across.push((number, (c..cols).take_while(|c| white(r, *c)).map(|c| r * cols + c).collect::<Vec<_>>()));
+				}
+				if starts_down {
+					down.push((number, (r..rows).take_while(|r| white(*r, c)).map(|r| r * cols + c).collect::<Vec<_>>()));
+				}
+			}
+		}
+
+		let mut words = vec![];
+		for (prefix, direction, numbered, mut clues) in [("h", "across", across, record.across), ("v", "down", down, record.down)] {
+			for (number, cells) in numbered {
+				let Some(clue) = clues.remove(&number) else {
+					anyhow::bail!("No clue for {} {}", direction, number);
+				};
+				words.push(Word {
+					label: format!("{prefix}{number}"),
+					clue,
+					cells,
+				});
+			}
+			if let Some(number) = clues.keys().next() {
+				anyhow::bail!("Clue {} {} has no word in the grid", direction, number);
+			}
+		}
+		Ok(Puzzle { rows, cols, solution, words })
+	}
+
+	/// Whether this is a 5x5 grid without black squares, the kind the built-in prompts and their examples describe.
+	pub fn is_mini(&self) -> bool {
+		self.rows == 5 && self.cols == 5 && self.white_cells() == 25
+	}
+
+	pub fn white_cells(&self) -> usize {
+		self.solution.iter().filter(|cell| *cell != BLACK).count()
+	}
+
+	/// The index of the word labelled `label`.
+	pub fn word(&self, label: &str) -> Option<usize> {
+		self.words.iter().position(|word| word.label == label)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn minis_are_numbered_by_row_and_column() {
+		let clues = (0..10).map(|i| format!("clue {i}")).collect::<Vec<_>>();
+		let puzzle = Puzzle::from_record(&serde_json::json!([clues, "abcdefghijklmnopqrstuvwxy".chars().map(String::from).collect::<Vec<_>>()])).unwrap();
+		assert!(puzzle.is_mini());
+		assert_eq!(puzzle.words.iter().map(|word| word.label.as_str()).collect::<Vec<_>>(), ["h1", "h2", "h3", "h4", "h5", "v1", "v2", "v3", "v4", "v5"]);
+		assert_eq!(puzzle.words[7].cells, [2, 7, 12, 17, 22]);
+		assert_eq!(puzzle.solution[1], "B");
+	}
+
+	#[test]
+	fn numbered_grids_follow_standard_numbering() {
+		let grid = ["CAT#", "A B L E", "R#EL"];
+		let record = serde_json::json!({
+			"grid": grid,
+			"across": {"1": "Pet", "4": "Capable", "6": "Eel-like letters"},
+			"down": {"1": "Vehicle", "2": "Not below", "3": "Narrative", "4": "Dawn"},
+		});
+		assert_eq!(Puzzle::from_record(&record).unwrap_err().to_string(), "No clue for down 5");
+
+		let record = serde_json::json!({
+			"title": "Cats",
+			"grid": grid,
+			"across": {"1": "Pet", "4": "Capable", "6": "Eel-like letters"},
+			"down": {"1": "Vehicle", "2": "Preposition", "3": "Story", "5": "Loose end"},
+		});
+		let puzzle = Puzzle::from_record(&record).unwrap();
+		assert!(!puzzle.is_mini());
+		assert_eq!((puzzle.rows, puzzle.cols, puzzle.white_cells()), (3, 4, 10));
+		let words = puzzle.words.iter().map(|word| (word.label.as_str(), word.cells.clone())).collect::<Vec<_>>();
+		assert_eq!(words, [("h1", vec![0, 1, 2]), ("h4", vec![4, 5, 6, 7]), ("h6", vec![10, 11]), ("v1", vec![0, 4, 8]), ("v2", vec![1, 5]), ("v3", vec![2, 6, 10]), ("v5", vec![7, 11])]);
+		assert_eq!(puzzle.word("v5"), Some(6));
+
+		let extra = serde_json::json!({"grid": ["AB", "CD"], "across": {"1": "a", "3": "b", "4": "c"}, "down": {"1": "d", "2": "e"}});
+		assert_eq!(Puzzle::from_record(&extra).unwrap_err().to_string(), "Clue across 4 has no word in the grid");
+	}
+}
@@ -0,0 +1,500 @@
+use super::{TOutput, TaskOptions, ToTTask};
+use crate::{
+	dataset::{DatasetSpec, Item},
+	models::{ModelConfig, Prompt},
+	prompts::Prompts,
+	strings::VOTE_PROMPT_TEXT,
+	template::Vars,
+	votes::{VoteExtractor, VoteOutcome},
+};
+use async_trait::async_trait;
+use regex::Regex;
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	process::Stdio,
+	sync::LazyLock,
+	time::Duration,
+};
+use tokio::{
+	io::{AsyncReadExt, AsyncWriteExt},
+	process::Command,
+};
+
+/// A task described entirely by a spec file (`--task_config`), for benchmarks that
+/// only differ from each other in data, prompts and answer checking.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct TaskSpec {
+	pub name: String,
+	pub steps: isize,
+	pub dataset: DatasetSpec,
+	pub prompts: PromptSpec,
+	/// Stop sequence for each step; missing or empty entries mean no stop.
+	#[serde(default)]
+	pub stops: Vec<String>,
+	/// Value per label found on the last line of a value output, e.g. `sure = 20.0`.
+	#[serde(default)]
+	pub value_labels: BTreeMap<String, f32>,
+	/// Reads a numeric score from value outputs instead of `value_labels`, and from the stdout of a
+	/// command verifier instead of taking all of it as the number; group 1 of the last match must hold it.
+	pub score_regex: Option<String>,
+	/// Overrides the "best choice is ..." conclusion pattern; group 1 must hold the choice.
+	pub vote_regex: Option<String>,
+	/// Extracts the answer from a candidate; group 1 of the last match is used.
+	/// Defaults to the last line with any `Answer:` prefix removed.
+	pub answer_regex: Option<String>,
+	pub verifier: VerifierSpec,
+	/// Directory relative paths in the spec are resolved against.
+	#[serde(skip)]
+	pub base_dir: PathBuf,
+}
+
+/// Prompt templates with `{{input}}` and `{{y}}` variables. A template without `{{y}}`
+/// gets the partial output appended, and a vote template without `{{choices}}` gets the numbered choices.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PromptSpec {
+	pub standard: String,
+	pub cot: Option<String>,
+	pub propose: Option<String>,
+	pub value: Option<String>,
+	/// Instruction placed before the numbered choices.
+	pub vote: Option<String>,
+}
+
+impl PromptSpec {
+	fn defaults(&self) -> Vec<(&str, &str)> {
+		let optional = [("cot", &self.cot), ("propose", &self.propose), ("value", &self.value)];
+		let mut defaults = vec![("standard", self.standard.as_str()), ("vote", self.vote.as_deref().unwrap_or(VOTE_PROMPT_TEXT))];
+		defaults.extend(optional.into_iter().filter_map(|(name, template)| Some((name, template.as_deref()?))));
+		defaults
+	}
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VerifierSpec {
+	Exact {
+		#[serde(default)]
+		case_sensitive: bool,
+	},
+	/// Passes when `pattern` matches the extracted answer; `{answer}` is replaced by the escaped gold answer.
+	Regex { pattern: String },
+	Numeric {
+		#[serde(default = "default_tolerance")]
+		tolerance: f64,
+	},
+	/// Runs `command` with a JSON object `{input, output, answer}` on stdin. A number on stdout
+	/// is used as the score; otherwise a zero exit status scores 1. Commands running longer
+	/// than `timeout` seconds, 10 by default, are killed and fail the puzzle.
+	Command {
+		command: Vec<String>,
+		#[serde(default = "default_timeout")]
+		timeout: f64,
+	},
+}
+
+fn default_tolerance() -> f64 {
+	1e-6
+}
+
+fn default_timeout() -> f64 {
+	10.0
+}
+
+impl TaskSpec {
+	pub fn load(path: &str) -> anyhow::Result<TaskSpec> {
+		let content = std::fs::read_to_string(path)?;
+		let mut spec: TaskSpec = match Path::new(path).extension().and_then(|e| e.to_str()) {
+			Some("toml") => toml::from_str(&content)?,
+			Some("yaml" | "yml") => serde_yaml::from_str(&content)?,
+			Some("json") => serde_json::from_str(&content)?,
+			ext => anyhow::bail!("Invalid task config extension: {:?}, expected toml, yaml or json", ext),
+		};
+		spec.base_dir = Path::new(path).parent().map(Path::to_path_buf).unwrap_or_default();
+		Ok(spec)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct DeclarativeTask {
+	spec: TaskSpec,
+	data: Vec<Item>,
+	prompts: Prompts,
+	answer_regex: Option<Regex>,
+	score_regex: Option<Regex>,
+	vote_extractor: VoteExtractor,
+	value_cache: BTreeMap<String, f32>,
+	/// Verifier scores by puzzle and candidate, so `is_solved` runs a command verifier once per candidate.
+	verdicts: BTreeMap<(usize, String), f32>,
+}
+
+impl DeclarativeTask {
+	/// Loads the dataset named in `spec` (relative to the spec file), or `options.file_path` when it is not empty.
+	/// Prompts in `<prompt_dir>/<name>/` override the ones in the spec.
+	pub fn new(spec: TaskSpec, options: &TaskOptions) -> anyhow::Result<DeclarativeTask> {
+		let data = options.dataset.load(spec.dataset.clone(), &options.file_path, &spec.base_dir)?;
+		let prompts = Prompts::load(&spec.name, &spec.prompts.defaults(), options)?;
+		let answer_regex = spec.answer_regex.as_deref().map(Regex::new).transpose()?;
+		let score_regex = spec.score_regex.as_deref().map(Regex::new).transpose()?;
+		let vote_extractor = match &spec.vote_regex {
+			Some(pattern) => VoteExtractor::with_conclusion(pattern)?,
+			None => VoteExtractor::new(),
+		};
+
+		Ok(DeclarativeTask {
+			spec,
+			data,
+			prompts,
+			answer_regex,
+			score_regex,
+			vote_extractor,
+			value_cache: BTreeMap::new(),
+			verdicts: BTreeMap::new(),
+		})
+	}
+
+	fn fill(&self, name: &str, x: &str, y: &str) -> Prompt {
+		let mut prompt = self.prompts.prompt(name, &Vars::new().text("input", x).text("y", y));
+		if !self.prompts.get(name).uses("y") {
+			prompt.push_str(y);
+		}
+		prompt
+	}
+
+	/// The value of one value output, by `score_regex` if the spec has one and by `value_labels` otherwise.
+	fn value_of(&self, output: &str) -> Option<f32> {
+		if self.score_regex.is_some() {
+			return self.extract_score(output);
+		}
+		let label = output.trim().lines().next_back()?.trim().to_lowercase();
+		self.spec.value_labels.iter().find(|(name, _)| name.to_lowercase() == label).map(|(_, value)| *value)
+	}
+
+	fn extract_answer<'a>(&self, output: &'a str) -> Option<&'a str> {
+		match &self.answer_regex {
+			Some(regex) => regex.captures_iter(output).last().and_then(|c| c.get(1)).map(|m| m.as_str().trim()),
+			None => {
+				let last_line = output.trim().lines().next_back()?;
+				let answer = last_line.split_once("Answer:").map(|(_, answer)| answer).unwrap_or(last_line);
+				Some(answer.trim())
+			}
+		}
+	}
+
+	/// The score of group 1 of the last `score_regex` match in `output`.
+	fn extract_score(&self, output: &str) -> Option<f32> {
+		let regex = self.score_regex.as_ref()?;
+		regex.captures_iter(output).last()?.get(1)?.as_str().trim().parse().ok()
+	}
+
+	async fn verify(&mut self, idx: usize, output: &str) -> anyhow::Result<f32> {
+		let key = (idx, output.to_string());
+		if let Some(score) = self.verdicts.get(&key) {
+			return Ok(*score);
+		}
+		let score = self.run_verifier(idx, output).await?;
+		self.verdicts.insert(key, score);
+		Ok(score)
+	}
+
+	async fn run_verifier(&self, idx: usize, output: &str) -> anyhow::Result<f32> {
+		let item = self.data.get(idx).ok_or(anyhow::anyhow!("Item not found"))?;
+		let answer = self.extract_answer(output);
+		let passed = match &self.spec.verifier {
+			VerifierSpec::Command { command, timeout } => {
+				let (stdout, success) = run_command(command, &item.input, output, item.answer.as_deref(), Duration::from_secs_f64(*timeout)).await?;
+				let score = match &self.score_regex {
+					Some(_) => self.extract_score(&stdout),
+					None => stdout.trim().parse().ok(),
+				};
+				return Ok(score.unwrap_or(if success { 1.0 } else { 0.0 }));
+			}
+			_ if answer.is_none() => false,
+			VerifierSpec::Exact { case_sensitive } => {
+				let gold = item.answer.as_deref().ok_or(anyhow::anyhow!("Exact verifier needs an answer_field"))?.trim();
+				if *case_sensitive {
+					answer == Some(gold)
+				} else {
+					answer.is_some_and(|a| a.to_lowercase() == gold.to_lowercase())
+				}
+			}
+			VerifierSpec::Regex { pattern } => {
+				let pattern = pattern.replace("{answer}", &regex::escape(item.answer.as_deref().unwrap_or("")));
+				Regex::new(&pattern)?.is_match(answer.unwrap_or(""))
+			}
+			VerifierSpec::Numeric { tolerance } => {
+				let gold = item.answer.as_deref().ok_or(anyhow::anyhow!("Numeric verifier needs an answer_field"))?;
+				match (first_number(answer.unwrap_or("")), first_number(gold)) {
+					(Some(a), Some(b)) => (a - b).abs() <= *tolerance,
+					_ => false,
+				}
+			}
+		};
+		Ok(if passed { 1.0 } else { 0.0 })
+	}
+}
+
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-?\d+(?:\.\d+)?").unwrap());
+
+fn first_number(s: &str) -> Option<f64> {
+	NUMBER.find(&s.replace(',', "")).and_then(|m| m.as_str().parse().ok())
+}
+
+/// Runs a command verifier and returns its stdout and whether it exited successfully.
+async fn run_command(command: &[String], input: &str, output: &str, answer: Option<&str>, timeout: Duration) -> anyhow::Result<(String, bool)> {
+	let Some((program, args)) = command.split_first() else {
+		anyhow::bail!("Command verifier needs a non-empty command");
+	};
+	let mut child = Command::new(program).args(args).stdin(Stdio::piped()).stdout(Stdio::piped()).kill_on_drop(true).spawn()?;
+	let payload = serde_json::json!({ "input": input, "output": output, "answer": answer }).to_string();
+	let mut stdin = child.stdin.take().expect("stdin is piped");
+	let mut stdout = child.stdout.take().expect("stdout is piped");
+	let run = async {
+		// stdin and stdout are served concurrently, so a verifier that writes before it has read
+		// all of its input cannot block on a full pipe while we wait on the other one
+		let write = async move {
+			// a verifier may exit without reading its input, which breaks the pipe but is not an error
+			let _ = stdin.write_all(payload.as_bytes()).await;
+		};
+		let mut buffer = vec![];
+		let (_, read) = tokio::join!(write, stdout.read_to_end(&mut buffer));
+		read?;
+		let status = child.wait().await?;
+		anyhow::Ok((buffer, status))
+	};
+	let Ok(result) = tokio::time::timeout(timeout, run).await else {
+		child.kill().await?;
+		anyhow::bail!("Command verifier {:?} timed out after {:?}", program, timeout);
+	};
+	let (stdout, status) = result?;
+	Ok((String::from_utf8_lossy(&stdout).into_owned(), status.success()))
+}
+
+#[async_trait]
+impl ToTTask for DeclarativeTask {
+	fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	fn get_steps(&self) -> isize {
+		self.spec.steps
+	}
+
+	fn get_stop(&self, step: usize) -> Option<&str> {
+		self.spec.stops.get(step).map(|s| s.as_str()).filter(|s| !s.is_empty())
+	}
+
+	fn get_input(&mut self, idx: usize) -> anyhow::Result<String> {
+		self.data.get(idx).map(|item| item.input.clone()).ok_or(anyhow::anyhow!("Item not found"))
+	}
+
+	fn get_id(&self, idx: usize) -> Option<String> {
+		self.data.get(idx).map(|item| item.id.clone())
+	}
+
+	fn standard_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		self.fill("standard", x, y)
+	}
+
+	fn cot_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		self.fill(if self.prompts.contains("cot") { "cot" } else { "standard" }, x, y)
+	}
+
+	fn propose_prompt_wrap(&mut self, x: &str, y: &str) -> anyhow::Result<Prompt> {
+		if !self.prompts.contains("propose") {
+			anyhow::bail!("Task {:?} has no propose prompt", self.spec.name);
+		}
+		Ok(self.fill("propose", x, y))
+	}
+
+	fn vote_prompt_wrap(&self, x: &str, ys: &[String]) -> Prompt {
+		let mut prompt = self.prompts.prompt("vote", &Vars::new().text("input", x).list("choices", ys));
+		if !self.prompts.get("vote").uses("choices") {
+			prompt.push_str(&choices(ys));
+		}
+		prompt
+	}
+
+	fn vote_outputs_unwrap(&self, vote_outputs: &[String], n_candidates: usize) -> (Vec<f32>, Vec<VoteOutcome>) {
+		self.vote_extractor.tally(vote_outputs, n_candidates)
+	}
+
+	async fn get_value(&mut self, x: &str, y: &str, model: &ModelConfig, n_evaluate_sample: isize, cache_value: bool) -> anyhow::Result<f32> {
+		if !self.prompts.contains("value") {
+			anyhow::bail!("Task {:?} has no value prompt", self.spec.name);
+		}
+		let value_prompt = self.fill("value", x, y);
+		let cache_key = value_prompt.to_string();
+		if cache_value {
+			if let Some(value) = self.value_cache.get(&cache_key) {
+				return Ok(*value);
+			}
+		}
+
+		let outputs = model.complete(&value_prompt, n_evaluate_sample, None).await?;
+		let value = outputs.iter().filter_map(|output| self.value_of(output)).sum();
+
+		if cache_value {
+			self.value_cache.insert(cache_key, value);
+		}
+		Ok(value)
+	}
+
+	async fn test_output(&mut self, idx: usize, output: &str, _scorer: &ModelConfig) -> anyhow::Result<TOutput> {
+		let mut info = TOutput::new();
+		info.r = self.verify(idx, output).await?;
+		info.correct = Some(info.r >= 1.0);
+		Ok(info)
+	}
+
+	async fn is_solved(&mut self, idx: usize, y: &str) -> bool {
+		self.verify(idx, y).await.is_ok_and(|r| r >= 1.0)
+	}
+}
+
+fn choices(ys: &[String]) -> String {
+	ys.iter().enumerate().map(|(i, y)| format!("Choice {}:\n{}\n", i + 1, y)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::time::Instant;
+
+	const SPEC: &str = "scripts/declarative/arithmetic.toml";
+
+	fn arithmetic(verifier: VerifierSpec, score_regex: Option<&str>) -> DeclarativeTask {
+		let mut spec = TaskSpec::load(SPEC).unwrap();
+		spec.verifier = verifier;
+		spec.score_regex = score_regex.map(str::to_string);
+		let options = TaskOptions {
+			prompt_dir: Some(PathBuf::from("no-prompt-overrides")),
+			..Default::default()
+		};
+		DeclarativeTask::new(spec, &options).unwrap()
+	}
+
+	fn command(script: &str, timeout: f64) -> VerifierSpec {
+		VerifierSpec::Command {
+			command: vec!["sh".to_string(), "-c".to_string(), script.to_string()],
+			timeout,
+		}
+	}
+
+	#[test]
+	fn loads_specs() {
+		let spec = TaskSpec::load(SPEC).unwrap();
+		assert_eq!(spec.name, "arithmetic");
+		assert_eq!(spec.steps, 2);
+		assert_eq!(spec.stops, ["\nAnswer:"]);
+		assert_eq!(spec.value_labels["sure"], 20.0);
+		assert!(matches!(spec.verifier, VerifierSpec::Numeric { tolerance } if tolerance == 1e-6));
+		assert_eq!(spec.base_dir, Path::new("scripts/declarative"));
+
+		let spec: TaskSpec = serde_json::from_str(
+			r#"{"name": "t", "steps": 1, "dataset": {"path": "t.jsonl"}, "prompts": {"standard": "{{input}}"},
+			"score_regex": "score: (\\d+)", "verifier": {"kind": "command", "command": ["true"]}}"#,
+		)
+		.unwrap();
+		assert_eq!(spec.dataset.input_field, "input");
+		assert!(matches!(spec.verifier, VerifierSpec::Command { timeout, .. } if timeout == 10.0));
+
+		let err = TaskSpec::load("Cargo.lock").err().unwrap();
+		assert!(err.to_string().starts_with("Invalid task config extension"), "{err}");
+	}
+
+	#[test]
+	fn renders_prompts() {
+		let mut task = arithmetic(VerifierSpec::Exact { case_sensitive: false }, None);
+		assert_eq!(task.len(), 3);
+		assert_eq!(task.get_stop(0), Some("\nAnswer:"));
+		assert_eq!(task.get_stop(1), None);
+		let x = task.get_input(0).unwrap();
+		assert_eq!(x, "What is 17 * 23?");
+
+		// a template without `{{y}}` gets the partial output appended
+		let standard = task.standard_prompt_wrap(&x, "17 * 23 = 391").to_string();
+		assert!(standard.starts_with("Answer the question."), "{standard}");
+		assert!(standard.ends_with("Question: What is 17 * 23?\n17 * 23 = 391"), "{standard}");
+		let cot = task.cot_prompt_wrap(&x, "17 * 20 = 340\n").to_string();
+		assert!(cot.ends_with("Steps:\n17 * 20 = 340\n"), "{cot}");
+
+		let vote = task.vote_prompt_wrap(&x, &["a".to_string(), "b".to_string()]).to_string();
+		assert!(vote.ends_with("Question: What is 17 * 23?\nChoice 1:\na\nChoice 2:\nb\n"), "{vote}");
+		assert!(task.propose_prompt_wrap(&x, "").is_err());
+	}
+
+	#[test]
+	fn reads_values_by_label_or_score_regex() {
+		let task = arithmetic(VerifierSpec::Exact { case_sensitive: false }, None);
+		assert_eq!(task.value_of("on track\nSure"), Some(20.0));
+		assert_eq!(task.value_of("no idea"), None);
+
+		let task = arithmetic(VerifierSpec::Exact { case_sensitive: false }, Some(r"(?i)score:\s*(\d+(?:\.\d+)?)"));
+		assert_eq!(task.value_of("Score: 3\nOn reflection, score: 7.5"), Some(7.5));
+		assert_eq!(task.value_of("sure"), None);
+	}
+
+	#[tokio::test]
+	async fn verifies_answers() {
+		let mut task = arithmetic(VerifierSpec::Numeric { tolerance: 1e-6 }, None);
+		assert!(task.is_solved(0, "17 * 23 = 391\nAnswer: 391").await);
+		assert!(!task.is_solved(0, "Answer: 390").await);
+		assert!(task.is_solved(1, "Answer: 19.0").await);
+
+		let mut task = arithmetic(VerifierSpec::Exact { case_sensitive: false }, None);
+		assert!(task.is_solved(2, "Answer: 108").await);
+		assert!(!task.is_solved(2, "108").await);
+		assert!(task.verify(3, "108").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn runs_command_verifiers() {
+		let mut task = arithmetic(command(r#"cat > /dev/null; echo 0.25"#, 10.0), None);
+		assert_eq!(task.verify(0, "Answer: 391").await.unwrap(), 0.25);
+		// the payload carries the candidate and the gold answer
+		let mut task = arithmetic(command(r#"input=$(cat); case "$input" in *'"answer":"391"'*) ;; *) exit 1;; esac; case "$input" in *'Answer: 391'*) exit 0;; esac; exit 1"#, 10.0), None);
+		assert_eq!(task.verify(0, "Answer: 391").await.unwrap(), 1.0);
+		assert_eq!(task.verify(0, "Answer: 390").await.unwrap(), 0.0);
+
+		let mut task = arithmetic(command("cat > /dev/null; echo 'checked 3 steps'; echo 'score: 0.5'", 10.0), Some(r"score: ([\d.]+)"));
+		assert_eq!(task.verify(0, "").await.unwrap(), 0.5);
+	}
+
+	#[tokio::test]
+	async fn command_verifiers_cannot_deadlock_or_hang() {
+		// fills the stdout pipe before reading a payload larger than the stdin pipe
+		let mut task = arithmetic(command("head -c 200000 /dev/zero; cat > /dev/null", 10.0), None);
+		assert_eq!(task.verify(0, &"x".repeat(1 << 20)).await.unwrap(), 1.0);
+
+		let mut task = arithmetic(command("sleep 5", 0.2), None);
+		let start = Instant::now();
+		let err = task.verify(0, "Answer: 391").await.err().unwrap();
+		assert!(err.to_string().contains("timed out"), "{err}");
+		assert!(start.elapsed() < Duration::from_secs(4));
+	}
+
+	#[tokio::test]
+	async fn command_verifiers_do_not_block_the_runtime() {
+		let mut task = arithmetic(command("cat > /dev/null; sleep 1", 10.0), None);
+		let start = Instant::now();
+		let (verdict, ticked) = tokio::join!(task.verify(0, "Answer: 391"), async {
+			tokio::time::sleep(Duration::from_millis(50)).await;
+			start.elapsed()
+		});
+		assert_eq!(verdict.unwrap(), 1.0);
+		assert!(ticked < Duration::from_millis(500), "{ticked:?}");
+	}
+
+	#[tokio::test]
+	async fn caches_verdicts() {
+		let log = std::env::temp_dir().join(format!("declarative-verdicts-{}", std::process::id()));
+		let mut task = arithmetic(command(&format!("cat > /dev/null; echo run >> {}", log.display()), 10.0), None);
+		assert!(task.is_solved(0, "Answer: 391").await);
+		assert!(task.is_solved(0, "Answer: 391").await);
+		assert!(task.is_solved(1, "Answer: 391").await);
+		let runs = std::fs::read_to_string(&log).unwrap();
+		std::fs::remove_file(&log).unwrap();
+		assert_eq!(runs.lines().count(), 2);
+	}
+}
@@ -0,0 +1,286 @@
+use super::{TOutput, TaskOptions, ToTTask, DATA_PATH};
+use crate::{
+	dataset::{DatasetFormat, DatasetSpec, Item},
+	models::{ModelConfig, Prompt},
+	prompts::Prompts,
+	strings,
+	template::Vars,
+};
+use async_trait::async_trait;
+use regex::Regex;
+use std::{path::Path, sync::LazyLock};
+
+#[derive(Debug, Clone)]
+pub struct Text {
+	data: Vec<Item>,
+	steps: isize,
+	n_score_sample: isize,
+	prompts: Prompts,
+}
+
+const PROMPTS: [(&str, &str); 4] = [
+	("standard", strings::STANDARD_PROMPT_TEXT),
+	("cot", strings::COT_PROMPT_TEXT),
+	("vote", strings::VOTE_PROMPT_TEXT),
+	("score", strings::SCORE_PROMPT_TEXT),
+];
+
+impl Text {
+	pub fn new(options: &TaskOptions) -> anyhow::Result<Text> {
+		let spec = DatasetSpec {
+			path: "data_100_random_text.txt".to_string(),
+			format: Some(DatasetFormat::Lines),
+			..Default::default()
+		};
+		let lines = options.dataset.load(spec, &options.file_path, &Path::new(DATA_PATH).join("text"))?;
+		let prompts = Prompts::load("text", &PROMPTS, options)?;
+
+		let n_score_sample = options.param("n_score_sample")?.unwrap_or(1);
+		if n_score_sample < 1 {
+			anyhow::bail!("Invalid n_score_sample {}, expected at least 1", n_score_sample);
+		}
+
+		Ok(Text {
+			data: lines,
+			steps: 2,
+			n_score_sample,
+			prompts,
+		})
+	}
+}
+
+/// Sentence-final punctuation with any closing quotes and the space after it.
+static SENTENCE_END: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"[.!?]+["”’')]*\s+"#).unwrap());
+/// A word whose period does not end a sentence: an initial or initialism like `J` or `U.S`, or a title like `Mr`.
+static ABBREVIATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:\p{Lu}\.)*\p{Lu}$|^(?i:mr|mrs|ms|dr|prof|st|jr|sr|mt|vs|e\.g|i\.e|fig|gen|capt|lt|col|sgt|rev)$").unwrap());
+static PARAGRAPH_BREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\n\s*\n").unwrap());
+
+/// The sentences of `text`, split after `.`, `!` or `?` and any closing quotes when a capital or an opening quote follows.
+/// A single period after an abbreviation such as `Mr.` or `U.S.` does not split, even where it also ends a sentence.
+fn sentences(text: &str) -> Vec<&str> {
+	let mut sentences = vec![];
+	let mut start = 0;
+	for m in SENTENCE_END.find_iter(text) {
+		let word = text[start..m.start()].split_whitespace().next_back().unwrap_or("").trim_start_matches(['"', '“', '‘', '\'', '(']);
+		if m.as_str().trim_end() == "." && ABBREVIATION.is_match(word) {
+			continue;
+		}
+		if text[m.end()..].starts_with(|c: char| c.is_uppercase() || "\"“‘'".contains(c)) {
+			sentences.push(text[start..m.end()].trim());
+			start = m.end();
+		}
+	}
+	sentences.push(text[start..].trim());
+	sentences.retain(|sentence| !sentence.is_empty());
+	sentences
+}
+
+/// Lowercase words without punctuation, so that quotes, apostrophes and spacing do not matter.
+fn normalise(text: &str) -> String {
+	let text = text.to_lowercase().chars().map(|c| if c.is_alphanumeric() { c } else if c.is_whitespace() { ' ' } else { '\0' }).filter(|c| *c != '\0').collect::<String>();
+	text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The paragraphs of `passage`, separated by blank lines, or by line breaks when that gives too few.
+fn paragraphs(passage: &str, expected: usize) -> Vec<&str> {
+	let blocks = PARAGRAPH_BREAK.split(passage.trim()).map(str::trim).filter(|block| !block.is_empty()).collect::<Vec<_>>();
+	if blocks.len() >= expected {
+		return blocks;
+	}
+	passage.lines().map(str::trim).filter(|line| !line.is_empty()).collect()
+}
+
+/// Share of the sentences of `input` that end the matching paragraph of `passage`, in order.
+/// Missing or extra paragraphs count as misses, so only a passage of exactly those paragraphs scores 1.
+pub fn constraint_score(input: &str, passage: &str) -> f32 {
+	let endings = sentences(input);
+	let paragraphs = paragraphs(passage, endings.len());
+	let met = endings
+		.iter()
+		.zip(&paragraphs)
+		.filter(|(ending, paragraph)| {
+			let (ending, paragraph) = (normalise(ending), normalise(paragraph));
+			!ending.is_empty() && (paragraph == ending || paragraph.ends_with(&format!(" {ending}")))
+		})
+		.count();
+	met as f32 / endings.len().max(paragraphs.len()).max(1) as f32
+}
+
+/// Ways scorers state the score, most specific first.
+static SCORE_PATTERNS: LazyLock<[Regex; 3]> = LazyLock::new(|| {
+	[
+		r"(?i)coheren\w*\s+score[\s*]*(?:is|of|:|=)?[\s*]*(\d+(?:\.\d+)?)",
+		r"(?i)score[\s*]*(?:is|of|:|=)[\s*]*(\d+(?:\.\d+)?)",
+		r"(?i)(\d+(?:\.\d+)?)\s*(?:/|out of)\s*10\b",
+	]
+	.map(|pattern| Regex::new(pattern).unwrap())
+});
+
+/// The 1-10 coherency score of a scorer output, like "the coherency score is 8", "**Coherency score:** 8" or "a score of 7/10".
+/// The last statement wins, since the prompt asks for the conclusion on the last line; fractions are rounded.
+pub fn extract_score(output: &str) -> Option<isize> {
+	let score = SCORE_PATTERNS.iter().find_map(|pattern| pattern.captures_iter(output).last().map(|captures| captures[1].to_string()))?;
+	let score = score.parse::<f32>().ok()?.round() as isize;
+	(1..=10).contains(&score).then_some(score)
+}
+
+#[async_trait]
+impl ToTTask for Text {
+	fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	fn get_steps(&self) -> isize {
+		self.steps
+	}
+
+	fn get_input(&mut self, idx: usize) -> anyhow::Result<String> {
+		self.data.get(idx).map(|item| item.input.clone()).ok_or(anyhow::anyhow!("Item not found"))
+	}
+
+	fn get_id(&self, idx: usize) -> Option<String> {
+		self.data.get(idx).map(|item| item.id.clone())
+	}
+
+	fn standard_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		self.prompts.prompt("standard", &Vars::new().text("input", x).text("y", y))
+	}
+
+	fn cot_prompt_wrap(&self, x: &str, y: &str) -> Prompt {
+		self.prompts.prompt("cot", &Vars::new().text("input", x).text("y", y))
+	}
+
+	fn vote_prompt_wrap(&self, x: &str, ys: &[String]) -> Prompt {
+		self.prompts.prompt("vote", &Vars::new().text("input", x).list("choices", ys))
+	}
+
+	/// Scores the coherence of the passage with `n_score_sample` scorer samples, and checks its paragraph endings into `r_constraint`.
+	async fn test_output(&mut self, idx: usize, output: &str, scorer: &ModelConfig) -> anyhow::Result<TOutput> {
+		let passage = output.split("Passage:\n").last().unwrap_or("");
+		let input = self.data.get(idx).ok_or(anyhow::anyhow!("Item not found"))?;
+		let prompt = self.prompts.prompt("score", &Vars::new().text("passage", passage));
+		let score_outputs = scorer.complete(&prompt, self.n_score_sample, None).await?;
+		Ok(score_passage(&input.input, passage, score_outputs))
+	}
+}
+
+/// The scores of `passage`, written for `input`, from the scorer's answers to its score prompt.
+fn score_passage(input: &str, passage: &str, score_outputs: Vec<String>) -> TOutput {
+	let mut info = TOutput::new();
+	info.r_constraint = constraint_score(input, passage);
+	// the coherence score is a judgement, not a verdict; only the paragraph endings can be checked
+	info.correct = Some(info.r_constraint == 1.0);
+	for score_output in score_outputs {
+		match extract_score(&score_output) {
+			Some(score) => info.rs.push(score),
+			None => info.unparsed.push(score_output),
+		}
+	}
+	let mut scores = info.rs.iter().map(|score| *score as f32).collect::<Vec<_>>();
+	scores.sort_by(f32::total_cmp);
+	if !scores.is_empty() {
+		let n = scores.len();
+		info.r = scores.iter().sum::<f32>() / n as f32;
+		info.r_median = if n % 2 == 1 { scores[n / 2] } else { (scores[n / 2 - 1] + scores[n / 2]) / 2.0 };
+		info.r_variance = scores.iter().map(|score| (score - info.r).powi(2)).sum::<f32>() / n as f32;
+	}
+	info
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_sentences() {
+		assert_eq!(sentences("It rained. We stayed in! Did you? Yes."), ["It rained.", "We stayed in!", "Did you?", "Yes."]);
+		// a lowercase word after the period does not start a sentence
+		assert_eq!(sentences("It cost 3.5 dollars, i.e. not much."), ["It cost 3.5 dollars, i.e. not much."]);
+	}
+
+	#[test]
+	fn abbreviations_do_not_end_sentences() {
+		assert_eq!(sentences("Mr. Smith joined the U.S. Army. He left."), ["Mr. Smith joined the U.S. Army.", "He left."]);
+		assert_eq!(sentences("Dr. Jones met J. R. R. Tolkien. Prof. Lee did not."), ["Dr. Jones met J. R. R. Tolkien.", "Prof. Lee did not."]);
+		// a plain word before the period still ends the sentence
+		assert_eq!(sentences("She said no. Then she left."), ["She said no.", "Then she left."]);
+	}
+
+	#[test]
+	fn quoted_endings_stay_with_their_sentence() {
+		let input = "People keep telling me \"orange\" but I still prefer \"pink\". He asked, \"Why not?\" She said \"Fine.\" 'Done,' he wrote.";
+		assert_eq!(sentences(input), ["People keep telling me \"orange\" but I still prefer \"pink\".", "He asked, \"Why not?\"", "She said \"Fine.\"", "'Done,' he wrote."]);
+		let passage = "Colours matter. People keep telling me “orange” but I still prefer “pink”.\n\nHe asked, “Why not?”\n\nShe said “Fine”.\n\nAnd then: ‘Done,’ he wrote.";
+		assert_eq!(constraint_score(input, passage), 1.0);
+	}
+
+	#[test]
+	fn scores_paragraph_endings() {
+		let input = "Mr. Smith joined the U.S. Army. He left.";
+		assert_eq!(constraint_score(input, "Years ago Mr. Smith joined the U.S. Army.\n\nThen, one day, he left."), 1.0);
+		// paragraphs may also be separated by single line breaks
+		assert_eq!(constraint_score(input, "Mr Smith joined the US Army\nHe left"), 1.0);
+		assert_eq!(constraint_score(input, "Mr. Smith joined the U.S. Army.\n\nHe stayed."), 0.5);
+		// the ending has to be the whole last sentence, not a suffix of a word
+		assert_eq!(constraint_score("He left.", "Cleft."), 0.0);
+	}
+
+	#[test]
+	fn mismatched_paragraph_counts_fall_short() {
+		let input = "It rained. We stayed in.";
+		assert_eq!(constraint_score(input, "It rained."), 0.5);
+		assert_eq!(constraint_score(input, "It rained.\n\nWe stayed in.\n\nThe end."), 2.0 / 3.0);
+		assert_eq!(constraint_score(input, "We stayed in.\n\nIt rained."), 0.0);
+		assert_eq!(constraint_score(input, ""), 0.0);
+	}
+
+	#[test]
+	fn extracts_scores() {
+		assert_eq!(extract_score("Thus the coherency score is 8"), Some(8));
+		assert_eq!(extract_score("**Coherency score:** 8"), Some(8));
+		assert_eq!(extract_score("Coherence score = 6."), Some(6));
+		assert_eq!(extract_score("I would give it 7/10."), Some(7));
+		assert_eq!(extract_score("7.5 out of 10"), Some(8));
+		assert_eq!(extract_score("The score is 10"), Some(10));
+		assert_eq!(extract_score("It reads well."), None);
+	}
+
+	#[test]
+	fn rejects_scores_out_of_range() {
+		assert_eq!(extract_score("The coherency score is 0"), None);
+		assert_eq!(extract_score("The coherency score is 11"), None);
+		assert_eq!(extract_score("12/10, would read again"), None);
+		assert_eq!(extract_score("The coherency score is 0.4"), None);
+	}
+
+	#[test]
+	fn the_last_score_wins() {
+		assert_eq!(extract_score("At first the coherency score is 4.\nOn reflection, the coherency score is 6"), Some(6));
+		assert_eq!(extract_score("Paragraph 2 is 3/10 and paragraph 4 is 9/10"), Some(9));
+		// an explicit coherency score beats a fraction after it
+		assert_eq!(extract_score("Coherency score: 5. Other judges gave 9/10."), Some(5));
+	}
+
+	#[test]
+	fn n_score_sample_is_a_task_param() {
+		assert_eq!(Text::new(&TaskOptions::default()).unwrap().n_score_sample, 1);
+		let mut options = TaskOptions::default();
+		options.extra.insert("n_score_sample".to_string(), "3".to_string());
+		assert_eq!(Text::new(&options).unwrap().n_score_sample, 3);
+		options.extra.insert("n_score_sample".to_string(), "0".to_string());
+		assert_eq!(Text::new(&options).err().unwrap().to_string(), "Invalid n_score_sample 0, expected at least 1");
+	}
+
+	#[test]
+	fn summarises_the_scores() {
+		let input = "It was dark. The end.";
+		let passage = "Night fell. It was dark.\n\nThey went home. The end.";
+		let score_outputs = ["The coherency score is 8", "9/10", "No score, sorry.", "**Coherency score:** 6"].map(str::to_string).to_vec();
+		let info = score_passage(input, passage, score_outputs);
+		assert_eq!(info.rs, [8, 9, 6]);
+		assert_eq!(info.unparsed, ["No score, sorry."]);
+		assert_eq!((info.r, info.r_median), (23.0 / 3.0, 8.0));
+		assert!((info.r_variance - 14.0 / 9.0).abs() < 1e-6, "{}", info.r_variance);
+		assert_eq!((info.r_constraint, info.correct), (1.0, Some(true)));
+	}
+}
@@ -0,0 +1,303 @@
+use async_openai::types::Role;
+use std::{borrow::Cow, collections::BTreeMap};
+
+/// A parsed prompt template.
+///
+/// - `{{name}}` inserts a variable. Inserted values are never expanded again, so data
+///   containing `{{input}}` or `{input}` comes through unchanged.
+/// - `{{#if name}}...{{else}}...{{/if}}` renders a branch on a flag, or on whether a text or list is non-empty.
+/// - `{{#each name}}...{{/each}}` repeats its body for every item of a list, with `{{item}}` and the 1-based `{{index}}`.
+/// - `{{@system}}`, `{{@user}}` and `{{@assistant}}` start a chat turn. They render as nothing in a flat
+///   prompt, so one template serves both modes; text before the first one belongs to the user.
+/// - `\{{` is a literal `{{`.
+///
+/// An unknown variable renders as its own tag so mistakes stay visible in the logged prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+	nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+	Text(String),
+	Var(String),
+	If { name: String, then: Vec<Node>, otherwise: Vec<Node> },
+	Each { name: String, body: Vec<Node> },
+	Turn(Role),
+}
+
+#[derive(Debug, Clone)]
+pub enum Value<'a> {
+	Text(Cow<'a, str>),
+	List(&'a [String]),
+	Flag(bool),
+}
+
+/// Variables available while rendering a template.
+#[derive(Debug, Clone, Default)]
+pub struct Vars<'a> {
+	values: BTreeMap<&'a str, Value<'a>>,
+}
+
+impl<'a> Vars<'a> {
+	pub fn new() -> Self {
+		Vars::default()
+	}
+
+	pub fn text(mut self, name: &'a str, value: &'a str) -> Self {
+		self.values.insert(name, Value::Text(Cow::Borrowed(value)));
+		self
+	}
+
+	pub fn list(mut self, name: &'a str, value: &'a [String]) -> Self {
+		self.values.insert(name, Value::List(value));
+		self
+	}
+
+	pub fn flag(mut self, name: &'a str, value: bool) -> Self {
+		self.values.insert(name, Value::Flag(value));
+		self
+	}
+
+	fn truthy(&self, name: &str) -> bool {
+		match self.values.get(name) {
+			Some(Value::Text(text)) => !text.is_empty(),
+			Some(Value::List(list)) => !list.is_empty(),
+			Some(Value::Flag(flag)) => *flag,
+			None => false,
+		}
+	}
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+	Text(String),
+	Var(String),
+	If(String),
+	Else,
+	EndIf,
+	Each(String),
+	EndEach,
+	Turn(Role),
+}
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token>> {
+	let mut tokens = vec![];
+	let mut text = String::new();
+	let mut rest = source;
+	while !rest.is_empty() {
+		if let Some(after) = rest.strip_prefix("\\{{") {
+			text.push_str("{{");
+			rest = after;
+		} else if let Some(after) = rest.strip_prefix("{{") {
+			let Some(end) = after.find("}}") else {
+				anyhow::bail!("Unclosed template tag near {:?}", rest.chars().take(30).collect::<String>());
+			};
+			if !text.is_empty() {
+				tokens.push(Token::Text(std::mem::take(&mut text)));
+			}
+			tokens.push(parse_tag(&after[..end])?);
+			rest = &after[end + 2..];
+		} else {
+			let c = rest.chars().next().unwrap();
+			text.push(c);
+			rest = &rest[c.len_utf8()..];
+		}
+	}
+	if !text.is_empty() {
+		tokens.push(Token::Text(text));
+	}
+	Ok(tokens)
+}
+
+fn parse_tag(tag: &str) -> anyhow::Result<Token> {
+	let valid_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+	let token = match tag.trim() {
+		"else" => Token::Else,
+		"/if" => Token::EndIf,
+		"/each" => Token::EndEach,
+		"@system" => Token::Turn(Role::System),
+		"@user" => Token::Turn(Role::User),
+		"@assistant" => Token::Turn(Role::Assistant),
+		tag => match tag.split_once(' ') {
+			Some(("#if", name)) if valid_name(name.trim()) => Token::If(name.trim().to_string()),
+			Some(("#each", name)) if valid_name(name.trim()) => Token::Each(name.trim().to_string()),
+			None if valid_name(tag) => Token::Var(tag.to_string()),
+			_ => anyhow::bail!("Invalid template tag: {:?}", tag),
+		},
+	};
+	Ok(token)
+}
+
+/// Parses nodes until one of `terminators` (returned) or the end of input (`None`).
+fn parse_nodes(tokens: &mut std::vec::IntoIter<Token>, terminators: &[Token]) -> anyhow::Result<(Vec<Node>, Option<Token>)> {
+	let mut nodes = vec![];
+	while let Some(token) = tokens.next() {
+		match token {
+			token if terminators.contains(&token) => return Ok((nodes, Some(token))),
+			Token::Text(text) => nodes.push(Node::Text(text)),
+			Token::Var(name) => nodes.push(Node::Var(name)),
+			Token::Turn(role) => nodes.push(Node::Turn(role)),
+			Token::If(name) => {
+				let (then, end) = parse_nodes(tokens, &[Token::Else, Token::EndIf])?;
+				let otherwise = match end {
+					Some(Token::Else) => match parse_nodes(tokens, &[Token::EndIf])? {
+						(otherwise, Some(_)) => otherwise,
+						_ => anyhow::bail!("Unclosed {{{{#if {}}}}} block in template", name),
+					},
+					Some(_) => vec![],
+					None => anyhow::bail!("Unclosed {{{{#if {}}}}} block in template", name),
+				};
+				nodes.push(Node::If { name, then, otherwise });
+			}
+			Token::Each(name) => {
+				let (body, end) = parse_nodes(tokens, &[Token::EndEach])?;
+				if end.is_none() {
+					anyhow::bail!("Unclosed {{{{#each {}}}}} block in template", name);
+				}
+				nodes.push(Node::Each { name, body });
+			}
+			token => anyhow::bail!("Unexpected {:?} in template", token),
+		}
+	}
+	Ok((nodes, None))
+}
+
+impl Template {
+	pub fn parse(source: &str) -> anyhow::Result<Template> {
+		let mut tokens = tokenize(source)?.into_iter();
+		let (nodes, _) = parse_nodes(&mut tokens, &[])?;
+		Ok(Template { nodes })
+	}
+
+	pub fn render(&self, vars: &Vars) -> String {
+		self.render_turns(vars).into_iter().map(|(_, text)| text).collect()
+	}
+
+	/// Renders the template split at its turn tags. Turns come back as written,
+	/// including empty ones and surrounding whitespace.
+	pub fn render_turns(&self, vars: &Vars) -> Vec<(Role, String)> {
+		let mut turns = vec![(Role::User, String::new())];
+		render_nodes(&self.nodes, vars, &mut turns);
+		turns
+	}
+
+	/// Whether the template refers to `name` anywhere, as a variable, condition or loop.
+	pub fn uses(&self, name: &str) -> bool {
+		fn visit(nodes: &[Node], name: &str) -> bool {
+			nodes.iter().any(|node| match node {
+				Node::Text(_) | Node::Turn(_) => false,
+				Node::Var(var) => var == name,
+				Node::If { name: var, then, otherwise } => var == name || visit(then, name) || visit(otherwise, name),
+				Node::Each { name: var, body } => var == name || visit(body, name),
+			})
+		}
+		visit(&self.nodes, name)
+	}
+}
+
+fn render_nodes(nodes: &[Node], vars: &Vars, turns: &mut Vec<(Role, String)>) {
+	fn out(turns: &mut [(Role, String)]) -> &mut String {
+		&mut turns.last_mut().expect("rendering starts in a turn").1
+	}
+	for node in nodes {
+		match node {
+			Node::Text(text) => out(turns).push_str(text),
+			Node::Var(name) => match vars.values.get(name.as_str()) {
+				Some(Value::Text(text)) => out(turns).push_str(text),
+				Some(Value::Flag(flag)) => out(turns).push_str(&flag.to_string()),
+				Some(Value::List(list)) => out(turns).push_str(&list.join("\n")),
+				None => out(turns).push_str(&format!("{{{{{name}}}}}")),
+			},
+			Node::If { name, then, otherwise } => render_nodes(if vars.truthy(name) { then } else { otherwise }, vars, turns),
+			Node::Each { name, body } => {
+				let Some(Value::List(items)) = vars.values.get(name.as_str()) else {
+					continue;
+				};
+				for (i, item) in items.iter().enumerate() {
+					let mut scope = vars.clone();
+					scope.values.insert("item", Value::Text(Cow::Borrowed(item)));
+					scope.values.insert("index", Value::Text(Cow::Owned((i + 1).to_string())));
+					render_nodes(body, &scope, turns);
+				}
+			}
+			Node::Turn(role) => turns.push((role.clone(), String::new())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn render(source: &str, vars: &Vars) -> String {
+		Template::parse(source).unwrap().render(vars)
+	}
+
+	#[test]
+	fn escapes_tags() {
+		let vars = Vars::new().text("input", "4 6");
+		assert_eq!(render(r"\{{input}} is {{input}}", &vars), "{{input}} is 4 6");
+		assert_eq!(render("{input} stays", &vars), "{input} stays");
+	}
+
+	#[test]
+	fn does_not_expand_inserted_values() {
+		let vars = Vars::new().text("input", "say {{y}} or {y}").text("y", "{{input}}");
+		assert_eq!(render("{{input}} / {{y}}", &vars), "say {{y}} or {y} / {{input}}");
+		// unknown variables stay visible
+		assert_eq!(render("{{missing}}", &vars), "{{missing}}");
+	}
+
+	#[test]
+	fn renders_conditions() {
+		let template = Template::parse("{{#if last}}Answer: {{ans}}{{else}}left: {{numbers}}{{/if}}").unwrap();
+		let vars = Vars::new().text("ans", "24").text("numbers", "4 6");
+		assert_eq!(template.render(&vars.clone().flag("last", true)), "Answer: 24");
+		assert_eq!(template.render(&vars.clone().flag("last", false)), "left: 4 6");
+		assert_eq!(template.render(&vars), "left: 4 6");
+		assert_eq!(render("{{#if y}}steps{{/if}}.", &Vars::new().text("y", "")), ".");
+	}
+
+	#[test]
+	fn repeats_each_item() {
+		let choices = vec!["a".to_string(), "b".to_string()];
+		let vars = Vars::new().list("choices", &choices).text("item", "outer");
+		assert_eq!(render("{{#each choices}}Choice {{index}}: {{item}}\n{{/each}}{{item}}", &vars), "Choice 1: a\nChoice 2: b\nouter");
+		assert_eq!(render("{{#if choices}}{{choices}}{{/if}}", &vars), "a\nb");
+		assert_eq!(render("[{{#each none}}{{item}}{{/each}}]", &vars), "[]");
+	}
+
+	#[test]
+	fn renders_turns() {
+		let template = Template::parse("{{@system}}Be brief.\n{{@user}}Q: {{input}}\n{{@assistant}}A: 24\n{{@user}}Q: next").unwrap();
+		let vars = Vars::new().text("input", "4 6");
+		assert_eq!(template.render(&vars), "Be brief.\nQ: 4 6\nA: 24\nQ: next");
+		assert_eq!(
+			template.render_turns(&vars),
+			vec![
+				(Role::User, String::new()),
+				(Role::System, "Be brief.\n".to_string()),
+				(Role::User, "Q: 4 6\n".to_string()),
+				(Role::Assistant, "A: 24\n".to_string()),
+				(Role::User, "Q: next".to_string()),
+			]
+		);
+		assert!(template.uses("input"));
+		assert!(!template.uses("y"));
+	}
+
+	#[test]
+	fn rejects_malformed_templates() {
+		let error = |source: &str| Template::parse(source).err().unwrap().to_string();
+		assert!(error("{{input").starts_with("Unclosed template tag"));
+		assert_eq!(error("{{#if x}}open"), "Unclosed {{#if x}} block in template");
+		assert_eq!(error("{{#if x}}a{{else}}b"), "Unclosed {{#if x}} block in template");
+		assert_eq!(error("{{#each xs}}open"), "Unclosed {{#each xs}} block in template");
+		assert_eq!(error("{{#if x}}a{{/each}}"), "Unexpected EndEach in template");
+		assert_eq!(error("{{/if}}"), "Unexpected EndIf in template");
+		assert_eq!(error("{{#while x}}"), "Invalid template tag: \"#while x\"");
+		assert_eq!(error("{{@tool}}"), "Invalid template tag: \"@tool\"");
+		assert_eq!(error("{{two words}}"), "Invalid template tag: \"two words\"");
+	}
+}
@@ -0,0 +1,176 @@
+use crate::models::MODELS;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use regex::Regex;
+use std::{
+	collections::{BTreeMap, HashMap},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, OnceLock},
+};
+
+/// Where `cl100k_base.tiktoken` (from the tiktoken project) is looked for unless `--tokenizer_dir` is given.
+/// The tree does not ship it; without it token counts are estimated, with a warning on first use.
+pub const DEFAULT_TOKENIZER_DIR: &str = "./data/tokenizers";
+
+/// cl100k pre-tokenizer without its `\s+(?!\S)` lookahead, which `regex` does not support;
+/// whitespace runs before a word can count one token more than the real tokenizer.
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+";
+
+static TOKENIZER_DIR: OnceLock<PathBuf> = OnceLock::new();
+static TOKENIZERS: Mutex<BTreeMap<String, Arc<Tokenizer>>> = Mutex::new(BTreeMap::new());
+
+/// Counts tokens with a tiktoken BPE file, or estimates them when the file is not available.
+#[derive(Debug)]
+pub enum Tokenizer {
+	Bpe { ranks: HashMap<Vec<u8>, u32>, pattern: Regex },
+	/// About three characters per token, which errs on the high side for English and code.
+	Estimate,
+}
+
+impl Tokenizer {
+	/// Loads a `.tiktoken` file: one base64 encoded token and its rank per line.
+	pub fn load(path: &Path) -> anyhow::Result<Tokenizer> {
+		let mut ranks = HashMap::new();
+		for line in std::fs::read_to_string(path)?.lines().filter(|line| !line.is_empty()) {
+			let Some((token, rank)) = line.split_once(' ') else {
+				anyhow::bail!("Invalid BPE line in {}: {:?}", path.display(), line);
+			};
+			ranks.insert(STANDARD.decode(token)?, rank.parse()?);
+		}
+		Ok(Tokenizer::Bpe {
+			ranks,
+			pattern: Regex::new(CL100K_PATTERN)?,
+		})
+	}
+
+	pub fn count(&self, text: &str) -> usize {
+		match self {
+			Tokenizer::Bpe { ranks, pattern } => pattern.find_iter(text).map(|piece| byte_pair_count(ranks, piece.as_str().as_bytes())).sum(),
+			Tokenizer::Estimate => text.chars().count().div_ceil(3),
+		}
+	}
+}
+
+/// Number of tokens `piece` merges into, always merging the lowest ranked pair first.
+fn byte_pair_count(ranks: &HashMap<Vec<u8>, u32>, piece: &[u8]) -> usize {
+	if piece.len() <= 1 || ranks.contains_key(piece) {
+		return piece.len().min(1);
+	}
+	// token boundaries; merging two tokens removes the boundary between them
+	let mut parts: Vec<usize> = (0..=piece.len()).collect();
+	while parts.len() > 2 {
+		let best = (0..parts.len() - 2).filter_map(|i| ranks.get(&piece[parts[i]..parts[i + 2]]).map(|rank| (*rank, i))).min();
+		let Some((_, i)) = best else {
+			break;
+		};
+		parts.remove(i + 1);
+	}
+	parts.len() - 1
+}
+
+/// The BPE encoding a model tokenizes with; every supported model uses cl100k.
+pub fn encoding(_model: &str) -> &'static str {
+	"cl100k_base"
+}
+
+/// Sets where `<encoding>.tiktoken` files are looked up and loads the ones the supported models use, failing if any is missing.
+/// Must be called before the first count; setting the same directory again is fine.
+pub fn set_tokenizer_dir(dir: &Path) -> anyhow::Result<()> {
+	if !dir.is_dir() {
+		anyhow::bail!("Tokenizer directory not found: {}", dir.display());
+	}
+	match TOKENIZER_DIR.get() {
+		Some(set) if set == dir => return Ok(()),
+		_ => TOKENIZER_DIR.set(dir.to_path_buf()).map_err(|_| anyhow::anyhow!("Tokenizer directory already set"))?,
+	}
+	let mut tokenizers = TOKENIZERS.lock().unwrap();
+	for model in MODELS {
+		let path = dir.join(format!("{}.tiktoken", encoding(model)));
+		let tokenizer = Tokenizer::load(&path).map_err(|e| anyhow::anyhow!("Could not load {}: {}", path.display(), e))?;
+		tokenizers.insert(encoding(model).to_string(), Arc::new(tokenizer));
+	}
+	Ok(())
+}
+
+/// The tokenizer for `model`, loaded on first use. Falls back to [`Tokenizer::Estimate`] without a BPE file,
+/// warning once per encoding.
+pub fn tokenizer(model: &str) -> Arc<Tokenizer> {
+	let encoding = encoding(model);
+	let mut tokenizers = TOKENIZERS.lock().unwrap();
+	let tokenizer = tokenizers.entry(encoding.to_string()).or_insert_with(|| {
+		let dir = TOKENIZER_DIR.get_or_init(|| PathBuf::from(DEFAULT_TOKENIZER_DIR));
+		let path = dir.join(format!("{encoding}.tiktoken"));
+		match Tokenizer::load(&path) {
+			Ok(tokenizer) => Arc::new(tokenizer),
+			Err(e) => {
+				// on stderr, so it also shows when stdout is piped, as with `solve`
+				eprintln!("Warning: could not load {} ({}), estimating token counts; pass --tokenizer_dir for exact ones", path.display(), e);
+				Arc::new(Tokenizer::Estimate)
+			}
+		}
+	});
+	tokenizer.clone()
+}
+
+/// Tokens `text` takes for `model`.
+pub fn count(model: &str, text: &str) -> usize {
+	tokenizer(model).count(text)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// A tokenizer whose vocabulary is the single bytes of `a b c` and space, plus `ranked` merges in rank order.
+	fn toy(ranked: &[&str]) -> Tokenizer {
+		let path = std::env::temp_dir().join(format!("toy-{}-{}.tiktoken", std::process::id(), ranked.join("_").replace(' ', "+")));
+		let tokens = ["a", "b", "c", " "].iter().chain(ranked);
+		let lines = tokens.enumerate().map(|(rank, token)| format!("{} {}\n", STANDARD.encode(token), rank)).collect::<String>();
+		std::fs::write(&path, lines).unwrap();
+		let tokenizer = Tokenizer::load(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		tokenizer
+	}
+
+	#[test]
+	fn estimates_three_characters_per_token() {
+		assert_eq!(Tokenizer::Estimate.count(""), 0);
+		assert_eq!(Tokenizer::Estimate.count("abc"), 1);
+		assert_eq!(Tokenizer::Estimate.count("abcd"), 2);
+		assert_eq!(Tokenizer::Estimate.count("é漢字"), 1);
+	}
+
+	#[test]
+	fn merges_the_lowest_ranked_pair_first() {
+		assert_eq!(toy(&[]).count("abc"), 3);
+		// `ab` outranks `bc`, and `abc` is not a token
+		assert_eq!(toy(&["ab", "bc"]).count("abc"), 2);
+		assert_eq!(toy(&["bc", "ab"]).count("abc"), 2);
+		assert_eq!(toy(&["ab", "abc"]).count("abc"), 1);
+		// a piece that is a token is never split
+		assert_eq!(toy(&["bc", "abc"]).count("abc"), 1);
+		// `bca` is only reached when `bc` merges before `ab` takes its `b`
+		assert_eq!(toy(&["ab", "bc", "bca"]).count("abca"), 3);
+		assert_eq!(toy(&["bc", "ab", "bca"]).count("abca"), 2);
+	}
+
+	#[test]
+	fn splits_words_before_merging() {
+		// `ab ab` splits into `ab` and ` ab`, which never merge across the split
+		let tokenizer = toy(&["ab", " ab"]);
+		assert_eq!(tokenizer.count("ab ab"), 2);
+		// without the lookahead the whole run of spaces is one piece: `ab`, two spaces and `ab`, one more than tiktoken
+		assert_eq!(tokenizer.count("ab  ab"), 4);
+		assert_eq!(toy(&["ab"]).count("ab ab"), 3);
+	}
+
+	#[test]
+	fn rejects_invalid_rank_files() {
+		let path = std::env::temp_dir().join(format!("invalid-{}.tiktoken", std::process::id()));
+		std::fs::write(&path, "YQ==\n").unwrap();
+		let err = Tokenizer::load(&path).err().unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert!(err.to_string().starts_with("Invalid BPE line"), "{err}");
+		assert!(Tokenizer::load(Path::new("no-such-file.tiktoken")).is_err());
+		assert!(set_tokenizer_dir(Path::new("no-such-dir")).is_err());
+	}
+}
@@ -0,0 +1,139 @@
+use regex::Regex;
+
+/// How a single vote output was interpreted.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VoteOutcome {
+	/// 0-based candidate indices; more than one entry means the voter declared a tie.
+	Vote { choices: Vec<usize> },
+	/// The voter explicitly declined to pick a candidate.
+	Abstain,
+	/// The voter named a choice number that does not exist.
+	OutOfRange { choice: usize },
+	/// No "best choice is ..." conclusion could be found.
+	ParseFailure { output: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct VoteExtractor {
+	conclusion: Regex,
+	choices: Regex,
+	number: Regex,
+	abstain: Regex,
+}
+
+impl Default for VoteExtractor {
+	fn default() -> Self {
+		VoteExtractor::new()
+	}
+}
+
+impl VoteExtractor {
+	pub fn new() -> Self {
+		VoteExtractor {
+			conclusion: Regex::new(r"(?i)best choices? (?:is|are)\s*:?\s*(.*)").unwrap(),
+			// a leading enumeration such as "Choice 12", "No. 2", "2 and 3" or "choices 1, 4 or 5"
+			choices: Regex::new(r"(?i)^\W*(?:(?:choices?|no\.?|number|#)\s*)?\d+(?:\s*(?:,|&|and|or|/)\s*(?:(?:choices?|no\.?|number|#)\s*)?\d+)*").unwrap(),
+			number: Regex::new(r"\d+").unwrap(),
+			abstain: Regex::new(r"(?i)^\W*(?:none|neither|no\b|n/a|unclear|undecided)").unwrap(),
+		}
+	}
+
+	/// Uses a custom conclusion pattern; its first capture group must hold the text naming the choice.
+	pub fn with_conclusion(pattern: &str) -> anyhow::Result<Self> {
+		Ok(VoteExtractor {
+			conclusion: Regex::new(pattern)?,
+			..VoteExtractor::new()
+		})
+	}
+
+	/// Interprets one vote output, using the last "best choice is" conclusion in it.
+	/// Choice numbers in the output are 1-based, as in `vote_prompt_wrap`.
+	pub fn extract(&self, output: &str, n_candidates: usize) -> VoteOutcome {
+		let Some(tail) = self.conclusion.captures_iter(output).last().and_then(|c| c.get(1)).map(|m| m.as_str()) else {
+			return VoteOutcome::ParseFailure { output: output.to_string() };
+		};
+		// choices first, so that "No. 2" is a vote and not a "no"
+		let Some(list) = self.choices.find(tail) else {
+			if self.abstain.is_match(tail) {
+				return VoteOutcome::Abstain;
+			}
+			return VoteOutcome::ParseFailure { output: output.to_string() };
+		};
+
+		let mut choices = vec![];
+		for choice in self.number.find_iter(list.as_str()).filter_map(|m| m.as_str().parse::<usize>().ok()) {
+			if choice == 0 || choice > n_candidates {
+				return VoteOutcome::OutOfRange { choice };
+			}
+			if !choices.contains(&(choice - 1)) {
+				choices.push(choice - 1);
+			}
+		}
+		VoteOutcome::Vote { choices }
+	}
+
+	/// Tallies every output into per-candidate scores. A tied vote is split evenly
+	/// between the tied candidates so each voter contributes at most one point.
+	pub fn tally(&self, outputs: &[String], n_candidates: usize) -> (Vec<f32>, Vec<VoteOutcome>) {
+		let mut results = vec![0.0; n_candidates];
+		let outcomes = outputs.iter().map(|o| self.extract(o, n_candidates)).collect::<Vec<_>>();
+		for outcome in &outcomes {
+			if let VoteOutcome::Vote { choices } = outcome {
+				for choice in choices {
+					results[*choice] += 1.0 / choices.len() as f32;
+				}
+			}
+		}
+		(results, outcomes)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn extract(output: &str, n_candidates: usize) -> VoteOutcome {
+		VoteExtractor::new().extract(output, n_candidates)
+	}
+
+	#[test]
+	fn choices_are_one_based_and_may_have_several_digits() {
+		assert_eq!(extract("Analysis...\nThe best choice is 1", 3), VoteOutcome::Vote { choices: vec![0] });
+		assert_eq!(extract("The best choice is Choice 12.", 12), VoteOutcome::Vote { choices: vec![11] });
+		assert_eq!(extract("The best choice is 10", 12), VoteOutcome::Vote { choices: vec![9] });
+		assert_eq!(extract("The best choice is 2, though 1 was close.\nThe best choice is 3", 3), VoteOutcome::Vote { choices: vec![2] });
+	}
+
+	#[test]
+	fn numbered_choices_are_not_abstentions() {
+		assert_eq!(extract("The best choice is No. 2", 3), VoteOutcome::Vote { choices: vec![1] });
+		assert_eq!(extract("The best choice is no 3", 3), VoteOutcome::Vote { choices: vec![2] });
+		assert_eq!(extract("The best choice is #1", 3), VoteOutcome::Vote { choices: vec![0] });
+	}
+
+	#[test]
+	fn ties_list_every_choice_once() {
+		assert_eq!(extract("The best choices are 2 and 3", 3), VoteOutcome::Vote { choices: vec![1, 2] });
+		assert_eq!(extract("The best choices are choices 1, 3 or 1", 3), VoteOutcome::Vote { choices: vec![0, 2] });
+	}
+
+	#[test]
+	fn abstentions_out_of_range_and_failures() {
+		for output in ["The best choice is none of them", "The best choice is: neither", "The best choice is no one choice", "The best choice is unclear"] {
+			assert_eq!(extract(output, 3), VoteOutcome::Abstain, "{output}");
+		}
+		assert_eq!(extract("The best choice is 0", 3), VoteOutcome::OutOfRange { choice: 0 });
+		assert_eq!(extract("The best choice is 4", 3), VoteOutcome::OutOfRange { choice: 4 });
+		assert_eq!(extract("I like the second one", 3), VoteOutcome::ParseFailure { output: "I like the second one".to_string() });
+		assert_eq!(extract("The best choice is the second", 3), VoteOutcome::ParseFailure { output: "The best choice is the second".to_string() });
+	}
+
+	#[test]
+	fn ties_split_one_point() {
+		let outputs = ["The best choice is 1", "The best choices are 1 and 2", "The best choice is none", "garbage"].map(String::from);
+		let (values, outcomes) = VoteExtractor::new().tally(&outputs, 3);
+		assert_eq!(values, [1.5, 0.5, 0.0]);
+		assert_eq!(outcomes[2], VoteOutcome::Abstain);
+	}
+}